@@ -0,0 +1,8 @@
+-- One-time nonces that a wallet signs to prove ownership
+
+CREATE TABLE IF NOT EXISTS auth_nonces (
+    nonce UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    wallet_address TEXT NOT NULL,
+    used BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,6 @@
+-- Optional public display name, unique regardless of case
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS display_name TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_display_name_lower_idx
+    ON users (LOWER(display_name));
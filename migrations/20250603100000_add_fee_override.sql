@@ -0,0 +1,4 @@
+-- Per-wallet claim fee for partners; NULL means use the global fee
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS fee_override_lamports BIGINT
+    CHECK (fee_override_lamports >= 0);
@@ -0,0 +1,14 @@
+-- Airdrop campaigns, each distributing its own token
+
+CREATE TABLE IF NOT EXISTS campaigns (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name TEXT NOT NULL,
+    token_mint TEXT NOT NULL,
+    airdrop_amount INT NOT NULL CHECK (airdrop_amount > 0),
+    is_active BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- At most one campaign can be live at a time
+CREATE UNIQUE INDEX IF NOT EXISTS campaigns_single_active_idx
+    ON campaigns (is_active) WHERE is_active;
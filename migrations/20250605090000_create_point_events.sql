@@ -0,0 +1,12 @@
+-- Ledger of every change to a user's points
+
+CREATE TABLE IF NOT EXISTS point_events (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id),
+    delta INT NOT NULL,
+    reason TEXT NOT NULL,
+    reference_id TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS point_events_user_idx ON point_events (user_id, created_at DESC);
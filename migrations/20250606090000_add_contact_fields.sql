@@ -0,0 +1,6 @@
+-- Optional contact details for claim receipts; nothing is sent unless the
+-- user opts in
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS contact_email TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS contact_webhook_url TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS notify_on_claim BOOLEAN NOT NULL DEFAULT FALSE;
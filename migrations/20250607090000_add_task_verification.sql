@@ -0,0 +1,7 @@
+-- Optional on-chain check a task must pass before it is credited. For
+-- 'onchain_balance' the wallet has to hold at least verify_min_amount whole
+-- tokens of verify_mint.
+
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS verify_type TEXT;
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS verify_mint TEXT;
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS verify_min_amount BIGINT;
@@ -0,0 +1,10 @@
+-- Batched points sync: with POINTS_BATCH_SYNC on, task completions record
+-- their points here unsynced and a periodic job folds them into
+-- users.total_points, instead of updating the hot users row per task.
+-- Existing rows are already counted in total_points.
+
+ALTER TABLE completed_tasks ADD COLUMN IF NOT EXISTS points_awarded INT NOT NULL DEFAULT 0;
+ALTER TABLE completed_tasks ADD COLUMN IF NOT EXISTS synced BOOLEAN NOT NULL DEFAULT TRUE;
+
+CREATE INDEX IF NOT EXISTS completed_tasks_unsynced_idx
+    ON completed_tasks (user_id) WHERE NOT synced;
@@ -0,0 +1,3 @@
+-- Task rewards can't be negative
+
+ALTER TABLE tasks ADD CONSTRAINT tasks_points_non_negative CHECK (points >= 0);
@@ -0,0 +1,21 @@
+-- Manually reviewed tasks: completing one stores a proof as a pending
+-- submission, and points are only credited once an admin approves it
+
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS requires_review BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS task_submissions (
+  id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+  user_id UUID NOT NULL REFERENCES users(id),
+  task_id UUID NOT NULL REFERENCES tasks(id),
+  proof TEXT NOT NULL,
+  status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'approved', 'rejected')),
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  reviewed_at TIMESTAMPTZ
+);
+
+-- At most one submission awaiting review per user and task
+CREATE UNIQUE INDEX IF NOT EXISTS task_submissions_one_pending_idx
+    ON task_submissions (user_id, task_id) WHERE status = 'pending';
+
+CREATE INDEX IF NOT EXISTS task_submissions_status_idx
+    ON task_submissions (status, created_at);
@@ -0,0 +1,4 @@
+-- Tasks that stay completable after a user has claimed. Such completions are
+-- recorded but award no points, since the claim already spent them.
+
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS post_claim_allowed BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,8 @@
+-- On-chain confirmation details for each airdrop, filled in after the
+-- transfer confirms. NULL until recorded (or if the lookup failed).
+
+ALTER TABLE airdrop_log
+    ADD COLUMN IF NOT EXISTS slot BIGINT,
+    ADD COLUMN IF NOT EXISTS block_time TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS fee_lamports BIGINT,
+    ADD COLUMN IF NOT EXISTS confirmation_status TEXT;
@@ -0,0 +1,6 @@
+-- Wallets allowed to register while BETA_ALLOWLIST is enabled
+
+CREATE TABLE IF NOT EXISTS beta_allowlist (
+    wallet_address TEXT PRIMARY KEY,
+    added_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
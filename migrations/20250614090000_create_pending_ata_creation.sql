@@ -0,0 +1,9 @@
+-- Wallets whose token account the background worker should create ahead of
+-- their claim (PRECREATE_ATAS). Rows are removed once the account exists.
+
+CREATE TABLE IF NOT EXISTS pending_ata_creation (
+    wallet_address TEXT PRIMARY KEY,
+    attempts INT NOT NULL DEFAULT 0,
+    last_error TEXT,
+    queued_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
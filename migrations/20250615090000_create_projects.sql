@@ -0,0 +1,29 @@
+-- Independent airdrop projects served by one deployment. Users, tasks and
+-- completions belong to exactly one project; existing rows move into
+-- 'default'. NULL settings fall back to the deployment-wide env config.
+
+CREATE TABLE IF NOT EXISTS projects (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    token_mint TEXT,
+    required_fee_lamports BIGINT CHECK (required_fee_lamports >= 0),
+    min_claim_points INT CHECK (min_claim_points >= 0),
+    airdrop_tokens BIGINT CHECK (airdrop_tokens > 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+INSERT INTO projects (id, name) VALUES ('default', 'Default') ON CONFLICT DO NOTHING;
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS project_id TEXT NOT NULL DEFAULT 'default' REFERENCES projects(id);
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS project_id TEXT NOT NULL DEFAULT 'default' REFERENCES projects(id);
+ALTER TABLE completed_tasks ADD COLUMN IF NOT EXISTS project_id TEXT NOT NULL DEFAULT 'default' REFERENCES projects(id);
+
+-- The same wallet may register separately with each project
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_wallet_address_key;
+CREATE UNIQUE INDEX IF NOT EXISTS users_project_wallet_idx ON users (project_id, wallet_address);
+
+DROP INDEX IF EXISTS users_display_name_lower_idx;
+CREATE UNIQUE INDEX IF NOT EXISTS users_project_display_name_lower_idx
+    ON users (project_id, lower(display_name));
+
+CREATE INDEX IF NOT EXISTS tasks_project_idx ON tasks (project_id);
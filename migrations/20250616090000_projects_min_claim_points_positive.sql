@@ -0,0 +1,4 @@
+-- Same rule as MIN_CLAIM_POINTS: a zero threshold would let every connected
+-- wallet claim
+ALTER TABLE projects DROP CONSTRAINT IF EXISTS projects_min_claim_points_check;
+ALTER TABLE projects ADD CONSTRAINT projects_min_claim_points_check CHECK (min_claim_points > 0);
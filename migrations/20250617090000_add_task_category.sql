@@ -0,0 +1,5 @@
+-- Lets the frontend group tasks, e.g. 'social', 'onchain', 'daily'
+
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS category TEXT NOT NULL DEFAULT 'general';
+
+CREATE INDEX IF NOT EXISTS tasks_project_category_idx ON tasks (project_id, category);
@@ -0,0 +1,4 @@
+-- Retired tasks are hidden and can't be completed, but stay in place so
+-- completed_tasks rows still reference them
+
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS is_active BOOLEAN NOT NULL DEFAULT TRUE;
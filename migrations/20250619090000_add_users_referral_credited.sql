@@ -0,0 +1,6 @@
+-- Referral points are held back until the referee claims. Existing links
+-- were credited when they were made, so they start out credited.
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS referral_credited BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE users SET referral_credited = TRUE WHERE referrer_id IS NOT NULL;
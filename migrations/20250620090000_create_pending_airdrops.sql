@@ -0,0 +1,18 @@
+-- Transfers that were submitted but not confirmed before the claim gave up
+-- waiting. The reconciler records them as claims once they land, or removes
+-- them once their blockhash has expired without them landing. Until then the
+-- wallet can't claim again, so the same airdrop is never sent twice.
+
+CREATE TABLE IF NOT EXISTS pending_airdrops (
+    signature TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL REFERENCES projects(id),
+    wallet_address TEXT NOT NULL,
+    mint TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    points INT NOT NULL,
+    fee_tx TEXT,
+    last_valid_block_height BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS pending_airdrops_wallet_idx ON pending_airdrops (project_id, wallet_address);
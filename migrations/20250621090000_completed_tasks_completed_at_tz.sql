@@ -0,0 +1,6 @@
+-- completed_at was a plain TIMESTAMP written in UTC; store it as TIMESTAMPTZ
+-- like every other timestamp. Rows that never recorded a time stay NULL.
+
+ALTER TABLE completed_tasks
+    ALTER COLUMN completed_at TYPE TIMESTAMPTZ USING completed_at AT TIME ZONE 'UTC',
+    ALTER COLUMN completed_at SET DEFAULT now();
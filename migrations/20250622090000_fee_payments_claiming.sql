@@ -0,0 +1,14 @@
+-- fee_payments predates the migrations; create it where it is missing so a
+-- fresh database matches existing ones.
+CREATE TABLE IF NOT EXISTS fee_payments (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    wallet_address TEXT NOT NULL,
+    tx_signature TEXT NOT NULL UNIQUE,
+    used BOOLEAN DEFAULT FALSE,
+    created_at TIMESTAMPTZ DEFAULT now()
+);
+
+-- Set while a claim holding the fee has a transfer in flight, so a second
+-- claim presenting the same fee is refused before it sends anything. Cleared
+-- when the claim is recorded (used becomes TRUE) or its transfer fails.
+ALTER TABLE fee_payments ADD COLUMN IF NOT EXISTS claiming BOOLEAN NOT NULL DEFAULT FALSE;
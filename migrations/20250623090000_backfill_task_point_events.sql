@@ -0,0 +1,10 @@
+-- Task points used to be recorded on completed_tasks alone. Enter the
+-- existing ones in the ledger so it accounts for every balance by itself.
+INSERT INTO point_events (user_id, delta, reason, reference_id, created_at)
+SELECT c.user_id, c.points_awarded, 'task', c.task_id::TEXT, COALESCE(c.completed_at, now())
+FROM completed_tasks c
+WHERE c.points_awarded <> 0
+  AND NOT EXISTS (
+      SELECT 1 FROM point_events e
+      WHERE e.user_id = c.user_id AND e.reason = 'task' AND e.reference_id = c.task_id::TEXT
+  );
@@ -0,0 +1,14 @@
+-- Campaigns and queued ATA creations belong to a project, like the rest of a
+-- claim's inputs; existing rows move into 'default'. Each project can run its
+-- own campaign, still at most one at a time.
+
+ALTER TABLE campaigns ADD COLUMN IF NOT EXISTS project_id TEXT NOT NULL DEFAULT 'default' REFERENCES projects(id);
+
+DROP INDEX IF EXISTS campaigns_single_active_idx;
+CREATE UNIQUE INDEX campaigns_single_active_idx ON campaigns (project_id) WHERE is_active;
+
+-- A wallet's token account depends on the project's mint, so a wallet can be
+-- queued once per project
+ALTER TABLE pending_ata_creation ADD COLUMN IF NOT EXISTS project_id TEXT NOT NULL DEFAULT 'default' REFERENCES projects(id);
+ALTER TABLE pending_ata_creation DROP CONSTRAINT IF EXISTS pending_ata_creation_pkey;
+ALTER TABLE pending_ata_creation ADD PRIMARY KEY (project_id, wallet_address);
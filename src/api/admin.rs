@@ -0,0 +1,499 @@
+use crate::{
+    api::{pagination::Pagination, tasks, user::CLAIMS_PAUSED},
+    config::CONFIG,
+    db,
+    error::{AppError, ErrorCode},
+    models::project::Project,
+    solana,
+};
+use axum::{
+    extract::{Path, Query, Request},
+    http::{header::AUTHORIZATION, StatusCode},
+    middleware::{self, Next},
+    response::Response,
+    routing::{get, post, put},
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::env;
+use std::sync::atomic::Ordering;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+struct FeeOverrideRequest {
+    // None clears the override
+    fee_override_lamports: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct AdjustPointsRequest {
+    delta: i32,
+    reason: String,
+}
+
+#[derive(Deserialize)]
+struct ClaimsPausedRequest {
+    paused: bool,
+}
+
+#[derive(Deserialize)]
+struct CreateTaskRequest {
+    name: String,
+    points: i32,
+    description: Option<String>,
+    category: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UpdateTaskRequest {
+    name: Option<String>,
+    points: Option<i32>,
+    description: Option<String>,
+    category: Option<String>,
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route(
+            "/api/admin/users/:wallet/fee_override",
+            post(set_fee_override),
+        )
+        .route(
+            "/api/admin/users/:wallet/adjust_points",
+            post(adjust_points),
+        )
+        .route(
+            "/api/admin/users/:wallet/resync_points",
+            post(resync_points),
+        )
+        .route("/api/admin/referral/:code", get(lookup_referral_code))
+        .route("/api/admin/claims/pause", post(set_claims_paused))
+        .route(
+            "/api/admin/beta/:wallet",
+            put(add_beta_wallet).delete(remove_beta_wallet),
+        )
+        .route("/api/admin/tasks", post(create_task))
+        .route(
+            "/api/admin/tasks/:id",
+            put(update_task).delete(deactivate_task),
+        )
+        .route("/api/admin/submissions", get(list_pending_submissions))
+        .route(
+            "/api/admin/submissions/:id/approve",
+            post(approve_submission),
+        )
+        .route("/api/admin/submissions/:id/reject", post(reject_submission))
+        .route_layer(middleware::from_fn(require_admin))
+}
+
+// Compare without short-circuiting so the token can't be guessed byte by byte
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// Rejects the request unless it carries `Authorization: Bearer <ADMIN_API_TOKEN>`.
+// With no token configured every admin request is refused.
+pub async fn require_admin(req: Request, next: Next) -> Result<Response, AppError> {
+    let expected = env::var("ADMIN_API_TOKEN").unwrap_or_default();
+
+    let provided = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or("");
+
+    if expected.is_empty() || !constant_time_eq(provided.as_bytes(), expected.as_bytes()) {
+        return Err(AppError::new(StatusCode::UNAUTHORIZED, "Unauthorized")
+            .with_code(ErrorCode::Unauthorized));
+    }
+
+    Ok(next.run(req).await)
+}
+
+async fn set_fee_override(
+    project: Project,
+    Path(wallet): Path<String>,
+    Json(req): Json<FeeOverrideRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let wallet = solana::normalize_wallet(&wallet);
+    if matches!(req.fee_override_lamports, Some(l) if l < 0) {
+        return Err(
+            AppError::new(StatusCode::BAD_REQUEST, "Fee override must be non-negative")
+                .with_code(ErrorCode::InvalidRequest),
+        );
+    }
+
+    let updated = db::set_fee_override(&project.id, &wallet, req.fee_override_lamports)
+        .await
+        .map_err(|_| {
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to set fee override",
+            )
+            .with_code(ErrorCode::DatabaseError)
+        })?;
+
+    if !updated {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Wallet not found")
+            .with_code(ErrorCode::WalletNotFound));
+    }
+
+    Ok(Json(json!({
+        "wallet": wallet,
+        "fee_override_lamports": req.fee_override_lamports
+    })))
+}
+
+async fn adjust_points(
+    project: Project,
+    Path(wallet): Path<String>,
+    Json(req): Json<AdjustPointsRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let wallet = solana::normalize_wallet(&wallet);
+    let reason = req.reason.trim();
+    if req.delta == 0 || reason.is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "A non-zero delta and a reason are required",
+        )
+        .with_code(ErrorCode::InvalidRequest));
+    }
+
+    let (old_total, new_total) = db::adjust_user_points(
+        &project.id,
+        &wallet,
+        req.delta,
+        reason,
+        CONFIG.max_user_points,
+    )
+    .await
+    .map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to adjust points")
+            .with_code(ErrorCode::DatabaseError)
+    })?
+    .ok_or_else(|| {
+        AppError::new(StatusCode::NOT_FOUND, "Wallet not found")
+            .with_code(ErrorCode::WalletNotFound)
+    })?;
+
+    tracing::info!(%wallet, delta = req.delta, old_total, new_total, reason, "Admin points adjustment");
+
+    Ok(Json(json!({
+        "wallet": wallet,
+        "old_total": old_total,
+        "new_total": new_total
+    })))
+}
+
+// Apply a user's batched task points immediately (see POINTS_BATCH_SYNC)
+async fn resync_points(
+    project: Project,
+    Path(wallet): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let wallet = solana::normalize_wallet(&wallet);
+    let db_error = |_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to resync points")
+            .with_code(ErrorCode::DatabaseError)
+    };
+
+    let user_id = db::get_user_id_by_wallet(&project.id, &wallet)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| {
+            AppError::new(StatusCode::NOT_FOUND, "Wallet not found")
+                .with_code(ErrorCode::WalletNotFound)
+        })?;
+
+    let total_points = db::resync_points(&user_id).await.map_err(db_error)?;
+
+    Ok(Json(json!({
+        "wallet": wallet,
+        "total_points": total_points
+    })))
+}
+
+async fn set_claims_paused(Json(req): Json<ClaimsPausedRequest>) -> Json<serde_json::Value> {
+    let was = CLAIMS_PAUSED.swap(req.paused, Ordering::Relaxed);
+    if was != req.paused {
+        tracing::warn!(paused = req.paused, "Claims pause toggled by admin");
+    }
+    Json(json!({ "claims_paused": req.paused }))
+}
+
+async fn add_beta_wallet(Path(wallet): Path<String>) -> Result<Json<serde_json::Value>, AppError> {
+    let wallet = solana::normalize_wallet(&wallet);
+    let added = db::add_beta_wallet(&wallet).await.map_err(|_| {
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to update beta allowlist",
+        )
+        .with_code(ErrorCode::DatabaseError)
+    })?;
+
+    Ok(Json(json!({ "wallet": wallet, "added": added })))
+}
+
+async fn remove_beta_wallet(
+    Path(wallet): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let wallet = solana::normalize_wallet(&wallet);
+    let removed = db::remove_beta_wallet(&wallet).await.map_err(|_| {
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to update beta allowlist",
+        )
+        .with_code(ErrorCode::DatabaseError)
+    })?;
+
+    Ok(Json(json!({ "wallet": wallet, "removed": removed })))
+}
+
+// Who owns a referral code (UUID or wallet form), with their stats
+async fn lookup_referral_code(
+    project: Project,
+    Path(code): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let code = solana::normalize_wallet(&code);
+    let db_error = |_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Referral lookup failed")
+            .with_code(ErrorCode::DatabaseError)
+    };
+    let not_found = || {
+        AppError::new(StatusCode::NOT_FOUND, "Referral code not found")
+            .with_code(ErrorCode::ReferralNotFound)
+    };
+
+    let user_id = db::get_user_id_by_referral_code(&project.id, &code)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(not_found)?;
+
+    let wallet = db::get_wallet_by_user_id(&user_id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(not_found)?;
+
+    let user_info = db::get_user_info(&project.id, &wallet)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(json!({
+        "referral_code": code,
+        "user": user_info
+    })))
+}
+
+// Guards against fat-fingered rewards (e.g. 1000000 instead of 100)
+fn validate_task_points(points: i32) -> Result<(), AppError> {
+    if !(0..=CONFIG.max_task_points).contains(&points) {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Task points must be between 0 and {}",
+                CONFIG.max_task_points
+            ),
+        )
+        .with_code(ErrorCode::InvalidRequest));
+    }
+    Ok(())
+}
+
+async fn create_task(
+    project: Project,
+    Json(req): Json<CreateTaskRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let name = req.name.trim();
+    if name.is_empty() {
+        return Err(
+            AppError::new(StatusCode::BAD_REQUEST, "Task name is required")
+                .with_code(ErrorCode::InvalidRequest),
+        );
+    }
+    validate_task_points(req.points)?;
+
+    let category = req.category.as_deref().and_then(tasks::normalize_category);
+    let task = db::create_task(
+        &project.id,
+        name,
+        req.points,
+        req.description.as_deref(),
+        category.as_deref(),
+    )
+    .await
+    .map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to create task")
+            .with_code(ErrorCode::DatabaseError)
+    })?;
+
+    tracing::info!(task_id = %task.id, name, points = task.points, "Task created");
+
+    Ok(Json(json!(task)))
+}
+
+async fn update_task(
+    project: Project,
+    Path(task_id): Path<Uuid>,
+    Json(req): Json<UpdateTaskRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let name = req.name.as_deref().map(str::trim);
+    if name == Some("") {
+        return Err(
+            AppError::new(StatusCode::BAD_REQUEST, "Task name cannot be empty")
+                .with_code(ErrorCode::InvalidRequest),
+        );
+    }
+    if let Some(points) = req.points {
+        validate_task_points(points)?;
+    }
+
+    let category = req.category.as_deref().and_then(tasks::normalize_category);
+    let task = db::update_task(
+        &project.id,
+        task_id,
+        name,
+        req.points,
+        req.description.as_deref(),
+        category.as_deref(),
+    )
+    .await
+    .map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to update task")
+            .with_code(ErrorCode::DatabaseError)
+    })?
+    .ok_or_else(|| {
+        AppError::new(StatusCode::NOT_FOUND, "Task not found").with_code(ErrorCode::TaskUnavailable)
+    })?;
+
+    tracing::info!(task_id = %task.id, points = task.points, "Task updated");
+
+    Ok(Json(json!(task)))
+}
+
+// Soft delete: completions that reference the task are kept
+async fn deactivate_task(
+    project: Project,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let deactivated = db::deactivate_task(&project.id, task_id)
+        .await
+        .map_err(|_| {
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to deactivate task",
+            )
+            .with_code(ErrorCode::DatabaseError)
+        })?;
+
+    if !deactivated {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Task not found")
+            .with_code(ErrorCode::TaskUnavailable));
+    }
+
+    tracing::info!(%task_id, "Task deactivated");
+
+    Ok(Json(json!({ "task_id": task_id, "is_active": false })))
+}
+
+// Reviewed-task proofs waiting for a decision, oldest first
+async fn list_pending_submissions(
+    project: Project,
+    Query(page): Query<Pagination>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let submissions = db::get_pending_submissions(&project.id, page.limit(), page.offset())
+        .await
+        .map_err(|_| {
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to fetch submissions",
+            )
+            .with_code(ErrorCode::DatabaseError)
+        })?;
+
+    Ok(Json(json!(submissions)))
+}
+
+async fn approve_submission(
+    project: Project,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    review_submission(&project, id, true).await
+}
+
+async fn reject_submission(
+    project: Project,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    review_submission(&project, id, false).await
+}
+
+async fn review_submission(
+    project: &Project,
+    id: Uuid,
+    approve: bool,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let submission = db::review_submission(&project.id, id, approve)
+        .await
+        .map_err(|_| {
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to review submission",
+            )
+            .with_code(ErrorCode::DatabaseError)
+        })?
+        .ok_or_else(|| {
+            AppError::new(StatusCode::NOT_FOUND, "No pending submission with that id")
+                .with_code(ErrorCode::InvalidRequest)
+        })?;
+
+    tracing::info!(submission_id = %id, status = %submission.status, wallet = %submission.wallet, "Task submission reviewed");
+
+    Ok(Json(json!(submission)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::block_on;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    const TOKEN: &str = "test-admin-token";
+
+    async fn list_submissions(authorization: Option<&str>) -> StatusCode {
+        let mut req = Request::get("/api/admin/submissions");
+        if let Some(value) = authorization {
+            req = req.header(AUTHORIZATION, value);
+        }
+        routes()
+            .oneshot(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[test]
+    fn admin_routes_need_the_bearer_token() {
+        env::set_var("ADMIN_API_TOKEN", TOKEN);
+        block_on(async {
+            assert_eq!(list_submissions(None).await, StatusCode::UNAUTHORIZED);
+            assert_eq!(
+                list_submissions(Some("Bearer wrong-token")).await,
+                StatusCode::UNAUTHORIZED
+            );
+            // The token alone, without the scheme, isn't accepted either
+            assert_eq!(
+                list_submissions(Some(TOKEN)).await,
+                StatusCode::UNAUTHORIZED
+            );
+            assert_eq!(
+                list_submissions(Some(&format!("Bearer {TOKEN}"))).await,
+                StatusCode::OK
+            );
+        });
+    }
+}
@@ -0,0 +1,30 @@
+use axum::{
+    extract::{Request, State},
+    http::{header::CACHE_CONTROL, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+// Mark successful responses as cacheable by browsers and CDNs for `max_age`
+// seconds. Errors are left alone so a transient failure isn't cached at the
+// edge; 0 turns caching off for the route.
+pub async fn public(State(max_age): State<u64>, req: Request, next: Next) -> Response {
+    let mut res = next.run(req).await;
+    if max_age > 0 && res.status().is_success() {
+        if let Ok(value) = HeaderValue::from_str(&format!("public, max-age={}", max_age)) {
+            res.headers_mut().insert(CACHE_CONTROL, value);
+        }
+    }
+    res
+}
+
+// Applied to the whole app: anything that didn't opt into caching above
+// (mutations, per-user data, errors) is never stored
+pub async fn no_store_by_default(req: Request, next: Next) -> Response {
+    let mut res = next.run(req).await;
+    if !res.headers().contains_key(CACHE_CONTROL) {
+        res.headers_mut()
+            .insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    }
+    res
+}
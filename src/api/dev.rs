@@ -0,0 +1,88 @@
+use crate::db;
+use crate::error::{AppError, ErrorCode};
+use crate::models::project::Project;
+use axum::extract::Query;
+use axum::http::StatusCode;
+use axum::{routing::get, Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+
+const MAX_BENCH_QUERIES: usize = 1000;
+
+#[derive(Deserialize)]
+struct BenchParams {
+    n: Option<usize>,
+}
+
+// Only mounted when DEV_ENDPOINTS=true, which config refuses in production
+pub fn routes() -> Router {
+    Router::new().route("/api/dev/bench", get(bench))
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> f64 {
+    let idx = ((sorted.len() as f64 - 1.0) * p).round() as usize;
+    sorted[idx].as_secs_f64() * 1000.0
+}
+
+// Runs `n` concurrent read-only get_user_info queries and reports latency
+async fn bench(
+    project: Project,
+    Query(params): Query<BenchParams>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let n = params.n.unwrap_or(100).clamp(1, MAX_BENCH_QUERIES);
+
+    let wallets = db::sample_wallets(&project.id, n as i64)
+        .await
+        .map_err(|_| {
+            AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to load wallets")
+                .with_code(ErrorCode::DatabaseError)
+        })?;
+    if wallets.is_empty() {
+        return Err(
+            AppError::new(StatusCode::BAD_REQUEST, "No users to benchmark against")
+                .with_code(ErrorCode::InvalidRequest),
+        );
+    }
+
+    let started = Instant::now();
+    let mut set = JoinSet::new();
+    for i in 0..n {
+        let wallet = wallets[i % wallets.len()].clone();
+        let project = project.id.clone();
+        set.spawn(async move {
+            let t = Instant::now();
+            let ok = db::get_user_info(&project, &wallet).await.is_ok();
+            (t.elapsed(), ok)
+        });
+    }
+
+    let mut timings = Vec::with_capacity(n);
+    let mut errors = 0;
+    while let Some(res) = set.join_next().await {
+        match res {
+            Ok((elapsed, true)) => timings.push(elapsed),
+            _ => errors += 1,
+        }
+    }
+    let total = started.elapsed();
+
+    if timings.is_empty() {
+        return Err(
+            AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Every query failed")
+                .with_code(ErrorCode::DatabaseError),
+        );
+    }
+    timings.sort();
+
+    Ok(Json(json!({
+        "queries": n,
+        "errors": errors,
+        "total_ms": total.as_secs_f64() * 1000.0,
+        "p50_ms": percentile(&timings, 0.50),
+        "p90_ms": percentile(&timings, 0.90),
+        "p99_ms": percentile(&timings, 0.99),
+        "max_ms": percentile(&timings, 1.0)
+    })))
+}
@@ -0,0 +1,38 @@
+use crate::events;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::{routing::get, Router};
+use std::convert::Infallible;
+use tokio_stream::{
+    wrappers::{BroadcastStream, WatchStream},
+    Stream, StreamExt,
+};
+
+pub fn routes() -> Router {
+    Router::new().route("/api/events/airdrops", get(airdrop_stream))
+}
+
+// Recent events are replayed first so a freshly opened page isn't empty.
+// The stream is dropped together with the receiver when the client
+// disconnects, and ends when the server starts shutting down.
+async fn airdrop_stream() -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let (replay, receiver) = events::subscribe_airdrops();
+    // Lagged behind the channel; skip what was missed and keep streaming
+    let live = BroadcastStream::new(receiver).filter_map(|msg| msg.ok());
+    let shutdown = WatchStream::new(events::subscribe_shutdown())
+        .filter(|closing| *closing)
+        .map(|_| None);
+    let stream = tokio_stream::iter(replay)
+        .chain(live)
+        .map(Some)
+        .merge(shutdown)
+        .take_while(Option::is_some)
+        .filter_map(|event| {
+            Event::default()
+                .event("airdrop")
+                .json_data(&event?)
+                .ok()
+                .map(Ok)
+        });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
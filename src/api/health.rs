@@ -0,0 +1,57 @@
+use crate::{config::CONFIG, db, solana, telemetry};
+use axum::{http::StatusCode, routing::get, Json, Router};
+use serde_json::json;
+
+// GET routes also answer HEAD (same status and headers, no body), and any
+// other method gets a 405 with an `Allow` header, so monitors and proxies
+// can probe these freely
+pub fn routes() -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/ready", get(ready))
+        .route("/metrics", get(metrics))
+}
+
+// Liveness: the process is up and serving. Touches nothing else, so a
+// database outage doesn't get the instance restarted.
+async fn health() -> Json<serde_json::Value> {
+    Json(json!({ "status": "ok" }))
+}
+
+// Readiness: 503 while the database (or, with READY_CHECK_RPC, the Solana
+// RPC) is unreachable, so traffic is routed elsewhere until it recovers
+async fn ready() -> (StatusCode, Json<serde_json::Value>) {
+    let database = db::ping().await.is_ok();
+
+    let rpc = if CONFIG.ready_check_rpc {
+        let ok = tokio::task::spawn_blocking(solana::ping_rpc)
+            .await
+            .map(|r| r.is_ok())
+            .unwrap_or(false);
+        Some(ok)
+    } else {
+        None
+    };
+
+    let ready = database && rpc != Some(false);
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    let state = |ok: bool| if ok { "ok" } else { "down" };
+
+    (
+        status,
+        Json(json!({
+            "status": if ready { "ready" } else { "unavailable" },
+            "database": state(database),
+            "rpc": rpc.map_or("skipped", state)
+        })),
+    )
+}
+
+// Prometheus scrape endpoint
+async fn metrics() -> String {
+    telemetry::render()
+}
@@ -0,0 +1,48 @@
+use crate::api::pagination::Pagination;
+use crate::db;
+use crate::error::{AppError, ErrorCode};
+use crate::models::project::Project;
+use axum::extract::Query;
+use axum::http::StatusCode;
+use axum::{routing::get, Json, Router};
+use serde_json::json;
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/api/leaderboard", get(get_leaderboard))
+        .route("/api/leaderboard/referrals", get(get_referral_leaderboard))
+}
+
+async fn get_leaderboard(
+    project: Project,
+    Query(page): Query<Pagination>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let entries = db::get_leaderboard(&project.id, page.limit(), page.offset())
+        .await
+        .map_err(|_| {
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to fetch leaderboard",
+            )
+            .with_code(ErrorCode::DatabaseError)
+        })?;
+
+    Ok(Json(json!(entries)))
+}
+
+async fn get_referral_leaderboard(
+    project: Project,
+    Query(page): Query<Pagination>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let entries = db::get_referral_leaderboard(&project.id, page.limit(), page.offset())
+        .await
+        .map_err(|_| {
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to fetch referral leaderboard",
+            )
+            .with_code(ErrorCode::DatabaseError)
+        })?;
+
+    Ok(Json(json!(entries)))
+}
@@ -1,2 +1,12 @@
+pub mod admin;
+pub mod cache;
+pub mod dev;
+pub mod events;
+pub mod health;
+pub mod leaderboard;
+pub mod pagination;
+pub mod project;
+pub mod referral;
+pub mod stats;
 pub mod tasks;
 pub mod user;
@@ -0,0 +1,90 @@
+use crate::{
+    config::CONFIG,
+    db,
+    error::{AppError, ErrorCode},
+    models::project::Project,
+};
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::HOST, request::Parts, StatusCode},
+};
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+pub const DEFAULT_PROJECT: &str = "default";
+
+// Projects change rarely, so lookups are cached briefly per id. Only projects
+// that exist are cached: the id comes from the client, and caching misses would
+// let anyone grow the map with made-up ids.
+const PROJECT_CACHE_TTL: Duration = Duration::from_secs(60);
+type ProjectCache = HashMap<String, (Project, Instant)>;
+static PROJECT_CACHE: Lazy<Mutex<ProjectCache>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Which project a request is for: the `X-Project-Id` header, else the
+// subdomain under PROJECT_DOMAIN, else 'default'. With MULTI_TENANT off every
+// request is 'default'. Unknown projects are rejected with 404.
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Project {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let id = if CONFIG.multi_tenant {
+            requested_project(parts).unwrap_or_else(|| DEFAULT_PROJECT.to_string())
+        } else {
+            DEFAULT_PROJECT.to_string()
+        };
+
+        lookup(&id).await?.ok_or_else(|| {
+            AppError::new(StatusCode::NOT_FOUND, format!("Unknown project: {}", id))
+                .with_code(ErrorCode::ProjectNotFound)
+        })
+    }
+}
+
+fn requested_project(parts: &Parts) -> Option<String> {
+    let header = parts
+        .headers
+        .get("x-project-id")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_ascii_lowercase())
+        .filter(|v| !v.is_empty());
+    if header.is_some() {
+        return header;
+    }
+
+    // e.g. Host "acme.airdrop.example" with PROJECT_DOMAIN=airdrop.example
+    let domain = CONFIG.project_domain.as_deref()?;
+    let host = parts.headers.get(HOST)?.to_str().ok()?;
+    let host = host.split(':').next().unwrap_or(host).to_ascii_lowercase();
+    let sub = host.strip_suffix(domain)?.strip_suffix('.')?;
+    (!sub.is_empty() && !sub.contains('.')).then(|| sub.to_string())
+}
+
+async fn lookup(id: &str) -> Result<Option<Project>, AppError> {
+    if let Some((project, at)) = PROJECT_CACHE.lock().unwrap().get(id) {
+        if at.elapsed() < PROJECT_CACHE_TTL {
+            return Ok(Some(project.clone()));
+        }
+    }
+
+    let project = db::get_project(id).await.map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Project lookup failed")
+            .with_code(ErrorCode::DatabaseError)
+    })?;
+    let mut cache = PROJECT_CACHE.lock().unwrap();
+    match &project {
+        Some(p) => {
+            cache.insert(id.to_string(), (p.clone(), Instant::now()));
+        }
+        // Deleted since it was cached
+        None => {
+            cache.remove(id);
+        }
+    }
+    Ok(project)
+}
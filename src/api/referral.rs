@@ -0,0 +1,56 @@
+use crate::db;
+use crate::error::{AppError, ErrorCode};
+use crate::models::{project::Project, user::public_wallet};
+use crate::solana;
+use axum::extract::Query;
+use axum::http::StatusCode;
+use axum::{routing::get, Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+struct ValidateQuery {
+    code: Option<String>,
+}
+
+pub fn routes() -> Router {
+    Router::new().route("/api/referral/validate", get(validate_referral_code))
+}
+
+// Lets the signup form check a code before connect_wallet. Read-only: nothing
+// is created or linked, and an unknown code is `valid: false`, not an error.
+async fn validate_referral_code(
+    project: Project,
+    Query(query): Query<ValidateQuery>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let code = query
+        .code
+        .as_deref()
+        .map(solana::normalize_wallet)
+        .filter(|c| !c.is_empty())
+        .ok_or_else(|| {
+            AppError::new(StatusCode::BAD_REQUEST, "Missing code param")
+                .with_code(ErrorCode::MissingParameter)
+        })?;
+
+    let db_error = |_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Referral lookup failed")
+            .with_code(ErrorCode::DatabaseError)
+    };
+
+    let Some(user_id) = db::get_user_id_by_referral_code(&project.id, &code)
+        .await
+        .map_err(db_error)?
+    else {
+        return Ok(Json(json!({ "valid": false })));
+    };
+
+    let referrer_wallet = db::get_wallet_by_user_id(&user_id)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(json!({
+        "valid": referrer_wallet.is_some(),
+        "referrer_wallet": referrer_wallet.as_deref().map(public_wallet)
+    })))
+}
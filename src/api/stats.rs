@@ -0,0 +1,29 @@
+use crate::api::cache;
+use crate::config::CONFIG;
+use crate::db;
+use crate::error::{AppError, ErrorCode};
+use axum::http::StatusCode;
+use axum::{middleware, routing::get, Json, Router};
+use serde_json::json;
+
+pub fn routes() -> Router {
+    Router::new().route(
+        "/api/stats/points",
+        get(get_points_stats).layer(middleware::from_fn_with_state(
+            CONFIG.cache_stats_max_age,
+            cache::public,
+        )),
+    )
+}
+
+async fn get_points_stats() -> Result<Json<serde_json::Value>, AppError> {
+    let totals = db::get_points_totals().await.map_err(|_| {
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to fetch points stats",
+        )
+        .with_code(ErrorCode::DatabaseError)
+    })?;
+
+    Ok(Json(json!(totals)))
+}
@@ -1,16 +1,59 @@
+use crate::api::{cache, pagination::Pagination};
+use crate::config::CONFIG;
 use crate::db;
-use crate::error::AppError;
+use crate::error::{AppError, ErrorCode};
+use crate::models::project::Project;
+use axum::extract::Query;
 use axum::http::StatusCode;
-use axum::{routing::get, Json, Router};
+use axum::{middleware, routing::get, Json, Router};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct TaskFilter {
+    min_points: Option<i32>,
+    category: Option<String>,
+}
 
 pub fn routes() -> Router {
-    Router::new().route("/api/tasks", get(get_tasks))
+    Router::new().route(
+        "/api/tasks",
+        get(get_tasks).layer(middleware::from_fn_with_state(
+            CONFIG.cache_tasks_max_age,
+            cache::public,
+        )),
+    )
 }
 
-async fn get_tasks() -> Result<Json<serde_json::Value>, AppError> {
-    let tasks = db::get_all_tasks()
-        .await
-        .map_err(|_| AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch tasks"))?;
+// `?limit=&offset=&min_points=&category=`; a page of 20 when omitted
+async fn get_tasks(
+    project: Project,
+    Query(page): Query<Pagination>,
+    Query(filter): Query<TaskFilter>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let category = filter.category.as_deref().and_then(normalize_category);
+    let (tasks, total) = db::get_tasks_paginated(
+        &project.id,
+        page.limit(),
+        page.offset(),
+        filter.min_points,
+        category.as_deref(),
+    )
+    .await
+    .map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch tasks")
+            .with_code(ErrorCode::DatabaseError)
+    })?;
+
+    Ok(Json(serde_json::json!({
+        "tasks": tasks,
+        "total": total,
+        "limit": page.limit(),
+        "offset": page.offset()
+    })))
+}
 
-    Ok(Json(serde_json::json!(tasks)))
+// Categories are stored and matched lowercase; blank means none
+pub fn normalize_category(category: &str) -> Option<String> {
+    let category = category.trim().to_ascii_lowercase();
+    (!category.is_empty()).then_some(category)
 }
@@ -1,102 +1,458 @@
-use axum::extract::Query;
+use axum::extract::{Query, Request};
 use axum::http::StatusCode;
 use axum::{
+    middleware::{self, Next},
+    response::Response,
     routing::{get, post},
     Json, Router,
 };
+use once_cell::sync::Lazy;
 use serde::Deserialize;
+use solana_sdk::hash::hash;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use uuid::Uuid;
 
-use crate::{db, error::AppError, solana};
+use crate::{
+    api::{cache, pagination::Pagination},
+    auth,
+    config::CONFIG,
+    db,
+    error::{AppError, ErrorCode},
+    geo,
+    models::{
+        airdrop::PendingAirdrop,
+        project::Project,
+        task::TaskError,
+        user::{ClaimError, ReferralLink, UserInfo},
+    },
+    notify::{self, Event},
+    rate_limit, solana, telemetry, verify,
+};
 use serde_json::json;
 
 #[derive(Deserialize)]
 struct ConnectWalletRequest {
+    #[serde(deserialize_with = "solana::deserialize_wallet")]
     wallet_address: String,
     referral_code: Option<String>,
+    // Proof of wallet ownership (see /api/user/nonce); required when
+    // REQUIRE_SIGNED_CONNECT is set, checked whenever present
+    nonce: Option<Uuid>,
+    signature: Option<String>,
 }
 
 #[derive(Deserialize)]
 struct CompleteTaskRequest {
+    #[serde(deserialize_with = "solana::deserialize_wallet")]
     wallet_address: String,
     task_id: Uuid,
+    // Required for tasks that are reviewed by hand
+    proof: Option<String>,
 }
 
 #[derive(Deserialize)]
 struct ClaimRequest {
+    #[serde(deserialize_with = "solana::deserialize_wallet")]
     wallet_address: String,
+    // Required when REQUIRE_SIGNED_CLAIM is set
+    nonce: Option<Uuid>,
+    signature: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct DisplayNameRequest {
+    #[serde(deserialize_with = "solana::deserialize_wallet")]
+    wallet_address: String,
+    display_name: String,
+    nonce: Uuid,
+    signature: String,
+}
+
+#[derive(Deserialize)]
+struct ContactRequest {
+    #[serde(deserialize_with = "solana::deserialize_wallet")]
+    wallet_address: String,
+    email: Option<String>,
+    webhook_url: Option<String>,
+    notify_on_claim: bool,
+    nonce: Uuid,
+    signature: String,
+}
+
+const MAX_PROOF_LEN: usize = 1000;
+const DISPLAY_NAME_MIN_LEN: usize = 3;
+const DISPLAY_NAME_MAX_LEN: usize = 20;
+const BLOCKED_NAME_WORDS: &[&str] = &[
+    "admin",
+    "moderator",
+    "support",
+    "official",
+    "fuck",
+    "shit",
+    "bitch",
+    "cunt",
+    "nigg",
+    "fag",
+    "rape",
+    "nazi",
+];
+
 pub fn routes() -> Router {
     Router::new()
         .route("/api/user/connect_wallet", post(connect_wallet))
-        .route("/api/user/complete_task", post(complete_task))
+        .route(
+            "/api/user/complete_task",
+            post(complete_task).layer(middleware::from_fn(rate_limit::limit_tasks_by_ip)),
+        )
         .route("/api/user/points", get(get_points))
-        .route("/api/user/claim_airdrop", post(claim_airdrop))
-        .route("/api/airdrop/stats", get(get_airdrop_stats))
+        .route("/api/user/rank", get(get_rank))
+        .route("/api/user/points_history", get(get_points_history))
+        .route("/api/user/completed_tasks", get(get_completed_tasks))
+        .route(
+            "/api/user/claim_airdrop",
+            post(claim_airdrop)
+                .layer(middleware::from_fn(require_claims_open))
+                .layer(middleware::from_fn(geo::block_restricted)),
+        )
+        .route("/api/user/claim_eligibility", get(get_claim_eligibility))
+        .route(
+            "/api/airdrop/stats",
+            get(get_airdrop_stats).layer(middleware::from_fn_with_state(
+                CONFIG.cache_stats_max_age,
+                cache::public,
+            )),
+        )
+        .route(
+            "/api/airdrop/treasury_balance",
+            get(get_treasury_balance).layer(middleware::from_fn_with_state(
+                CONFIG.cache_stats_max_age,
+                cache::public,
+            )),
+        )
         .route("/api/user/referral_code", get(get_referral_code))
+        .route("/api/user/nonce", get(get_nonce))
+        // The same challenge, under the name connect_wallet clients ask for
+        .route("/api/user/login_challenge", get(get_nonce))
+        .route("/api/user/display_name", post(set_display_name))
+        .route("/api/user/contact", post(set_contact))
 }
 
 pub async fn connect_wallet(
+    project: Project,
     Json(req): Json<ConnectWalletRequest>,
 ) -> Result<Json<serde_json::Value>, AppError> {
-    let user_id = db::create_user(&req.wallet_address)
+    solana::validate_wallet(&req.wallet_address)?;
+
+    // Without this anyone could register a wallet they don't hold and attach
+    // it to their own referral code
+    match (req.nonce, req.signature.as_deref()) {
+        (Some(nonce), Some(signature)) => {
+            solana::verify_signed_message(&req.wallet_address, &nonce, signature).await?
+        }
+        _ if CONFIG.require_signed_connect => {
+            return Err(AppError::new(
+                StatusCode::UNAUTHORIZED,
+                "Connect must be signed: request a nonce and include nonce and signature",
+            )
+            .with_code(ErrorCode::InvalidSignature));
+        }
+        _ => {}
+    }
+
+    if CONFIG.beta_allowlist {
+        let allowed = db::is_beta_wallet(&req.wallet_address).await.map_err(|_| {
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Beta allowlist lookup failed",
+            )
+            .with_code(ErrorCode::DatabaseError)
+        })?;
+        if !allowed {
+            return Err(
+                AppError::new(StatusCode::FORBIDDEN, "Not in beta").with_code(ErrorCode::NotInBeta)
+            );
+        }
+    }
+
+    let user_id = db::create_user(&project.id, &req.wallet_address)
         .await
-        .map_err(|_| AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to create user"))?;
+        .map_err(|_| {
+            AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to create user")
+                .with_code(ErrorCode::DatabaseError)
+        })?;
 
     if let Some(ref_code) = req.referral_code {
-        if let Some(referrer_id) =
-            db::get_user_id_by_referral_code(&ref_code)
-                .await
-                .map_err(|_| {
-                    AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Referral lookup failed")
-                })?
+        if let Some(referrer_id) = db::get_user_id_by_referral_code(&project.id, &ref_code)
+            .await
+            .map_err(|_| {
+                AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Referral lookup failed")
+                    .with_code(ErrorCode::DatabaseError)
+            })?
         {
-            db::set_referrer(&user_id, &referrer_id)
+            let cycle_depth = CONFIG
+                .referral_cycle_check
+                .then_some(CONFIG.referral_max_depth);
+            let link = db::set_referrer(&user_id, &referrer_id, cycle_depth)
                 .await
                 .map_err(|_| {
                     AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to link referrer")
+                        .with_code(ErrorCode::DatabaseError)
                 })?;
 
-            db::add_referral_points(&referrer_id).await.map_err(|_| {
-                AppError::new(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Failed to credit referral",
-                )
-            })?;
+            match link {
+                // Nothing is credited yet: the referrer's points are held
+                // until this wallet claims, so throwaway wallets earn nothing.
+                // Reconnecting with a code leaves an existing link alone.
+                ReferralLink::Linked | ReferralLink::AlreadyReferred => {}
+                // Covers both code forms: pasting your own wallet address or
+                // your own referral UUID resolves back to your own id
+                ReferralLink::SelfReferral => {
+                    return Err(
+                        AppError::new(StatusCode::BAD_REQUEST, "You cannot refer yourself")
+                            .with_code(ErrorCode::SelfReferral),
+                    )
+                }
+                ReferralLink::Cycle => {
+                    return Err(AppError::new(
+                        StatusCode::BAD_REQUEST,
+                        "This referral would create a referral loop",
+                    )
+                    .with_code(ErrorCode::ReferralCycle))
+                }
+            }
         }
     }
 
     Ok(Json(serde_json::json!({ "status": "wallet connected" })))
 }
 
+fn task_not_found() -> AppError {
+    AppError::new(StatusCode::NOT_FOUND, "Task not found").with_code(ErrorCode::TaskUnavailable)
+}
+
+fn task_error(e: TaskError, task_id: Uuid) -> AppError {
+    match e {
+        TaskError::UserNotFound => {
+            AppError::new(StatusCode::NOT_FOUND, "Wallet not found; connect it first")
+                .with_code(ErrorCode::WalletNotFound)
+        }
+        TaskError::TaskNotFound => task_not_found(),
+        TaskError::AlreadyClaimed => AppError::new(
+            StatusCode::FORBIDDEN,
+            "Airdrop already claimed; this task no longer earns points",
+        )
+        .with_code(ErrorCode::AlreadyClaimed),
+        TaskError::AlreadyCompleted => {
+            AppError::new(StatusCode::CONFLICT, "Task already completed")
+                .with_code(ErrorCode::TaskAlreadyCompleted)
+        }
+        TaskError::AlreadySubmitted => AppError::new(
+            StatusCode::CONFLICT,
+            "A proof for this task is awaiting review",
+        )
+        .with_code(ErrorCode::SubmissionPending),
+        TaskError::Db(e) => {
+            tracing::error!(error = %e, %task_id, "Task completion failed");
+            AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "DB error")
+                .with_code(ErrorCode::DatabaseError)
+        }
+    }
+}
+
 pub async fn complete_task(
+    project: Project,
     Json(req): Json<CompleteTaskRequest>,
 ) -> Result<Json<serde_json::Value>, AppError> {
-    db::complete_task(&req.wallet_address, req.task_id)
+    solana::validate_wallet(&req.wallet_address)?;
+
+    // Bots hammer this to race completions; checked before any DB work
+    rate_limit::TASK_WALLET_LIMITER
+        .check(&req.wallet_address)
+        .map_err(rate_limit::too_many_requests)?;
+
+    let verification = db::get_task_verification(&project.id, req.task_id)
+        .await
+        .map_err(|_| {
+            AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Task lookup failed")
+                .with_code(ErrorCode::DatabaseError)
+        })?
+        .ok_or_else(task_not_found)?;
+
+    if !verification.is_active {
+        return Err(
+            AppError::new(StatusCode::GONE, "This task is no longer available")
+                .with_code(ErrorCode::TaskInactive),
+        );
+    }
+
+    verify::check_task(&req.wallet_address, &verification).await?;
+
+    if verification.requires_review {
+        let proof = req.proof.as_deref().map(str::trim).unwrap_or("");
+        if proof.is_empty() || proof.chars().count() > MAX_PROOF_LEN {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                format!("This task needs a proof of 1-{} characters", MAX_PROOF_LEN),
+            )
+            .with_code(ErrorCode::InvalidRequest));
+        }
+
+        let submission_id =
+            db::submit_task_proof(&project.id, &req.wallet_address, req.task_id, proof)
+                .await
+                .map_err(|e| task_error(e, req.task_id))?;
+
+        return Ok(Json(serde_json::json!({
+            "status": "submitted for review",
+            "submission_id": submission_id
+        })));
+    }
+
+    let completion = db::complete_task(&project.id, &req.wallet_address, req.task_id)
         .await
-        .map_err(|_| AppError::new(StatusCode::BAD_REQUEST, "Task already completed or invalid"))?;
+        .map_err(|e| task_error(e, req.task_id))?;
+
+    notify::publish(Event::TaskCompleted {
+        wallet: req.wallet_address.clone(),
+        task_id: req.task_id,
+        points: completion.points_awarded,
+    });
 
-    Ok(Json(serde_json::json!({ "status": "task recorded" })))
+    // Wallets working through tasks are the ones likely to claim
+    if CONFIG.precreate_atas {
+        if let Err(e) = db::queue_ata_creation(&project.id, &req.wallet_address).await {
+            tracing::warn!(wallet = %req.wallet_address, error = %e, "Failed to queue ATA creation");
+        }
+    }
+
+    Ok(Json(serde_json::json!({
+        "status": "task recorded",
+        "points_awarded": completion.points_awarded,
+        "total_points": completion.total_points
+    })))
+}
+
+/// Reads the required `wallet` query parameter, normalized and validated.
+fn wallet_param(params: &HashMap<String, String>) -> Result<String, AppError> {
+    let wallet = params
+        .get("wallet")
+        .map(|w| solana::normalize_wallet(w))
+        .ok_or_else(|| {
+            AppError::new(StatusCode::BAD_REQUEST, "Missing wallet param")
+                .with_code(ErrorCode::MissingParameter)
+        })?;
+    solana::validate_wallet(&wallet)?;
+    Ok(wallet)
 }
 
 pub async fn get_points(
+    project: Project,
     Query(params): Query<HashMap<String, String>>,
 ) -> Result<Json<serde_json::Value>, AppError> {
-    let wallet = params
-        .get("wallet")
-        .ok_or_else(|| AppError::new(StatusCode::BAD_REQUEST, "Missing wallet param"))?;
+    let wallet = wallet_param(&params)?;
 
-    let user_info = db::get_user_info(wallet)
-        .await
-        .map_err(|_| AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "DB error"))?;
+    let user_info = db::get_user_info(&project.id, &wallet).await.map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "DB error")
+            .with_code(ErrorCode::DatabaseError)
+    })?;
 
     Ok(Json(serde_json::json!(user_info)))
 }
 
+// Every change to the wallet's points, newest first, so support can explain
+// a balance
+pub async fn get_points_history(
+    project: Project,
+    Query(params): Query<HashMap<String, String>>,
+    Query(page): Query<Pagination>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let wallet = wallet_param(&params)?;
+
+    let db_error = |_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "DB error")
+            .with_code(ErrorCode::DatabaseError)
+    };
+
+    if db::get_user_id_by_wallet(&project.id, &wallet)
+        .await
+        .map_err(db_error)?
+        .is_none()
+    {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Wallet not found")
+            .with_code(ErrorCode::WalletNotFound));
+    }
+
+    let history = db::get_points_history(&project.id, &wallet, page.limit(), page.offset())
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(json!({
+        "wallet": wallet,
+        "history": history
+    })))
+}
+
+// The wallet's completed tasks with names and times, so clients needn't
+// match tasks_completed ids against /api/tasks
+pub async fn get_completed_tasks(
+    project: Project,
+    Query(params): Query<HashMap<String, String>>,
+    Query(page): Query<Pagination>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let wallet = wallet_param(&params)?;
+
+    let db_error = |_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "DB error")
+            .with_code(ErrorCode::DatabaseError)
+    };
+
+    if db::get_user_id_by_wallet(&project.id, &wallet)
+        .await
+        .map_err(db_error)?
+        .is_none()
+    {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Wallet not found")
+            .with_code(ErrorCode::WalletNotFound));
+    }
+
+    let tasks = db::get_completed_tasks(&project.id, &wallet, page.limit(), page.offset())
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(json!({
+        "wallet": wallet,
+        "completed_tasks": tasks
+    })))
+}
+
+// Where the wallet stands on /api/leaderboard
+pub async fn get_rank(
+    project: Project,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let wallet = wallet_param(&params)?;
+
+    let (rank, total_points) = db::get_user_rank(&project.id, &wallet)
+        .await
+        .map_err(|_| {
+            AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "DB error")
+                .with_code(ErrorCode::DatabaseError)
+        })?
+        .ok_or_else(|| {
+            AppError::new(StatusCode::NOT_FOUND, "Wallet not found")
+                .with_code(ErrorCode::WalletNotFound)
+        })?;
+
+    Ok(Json(json!({
+        "wallet": wallet,
+        "rank": rank,
+        "total_points": total_points
+    })))
+}
+
 pub async fn get_airdrop_stats() -> Json<serde_json::Value> {
-    let wallet_count = db::get_wallet_count().await.unwrap_or(0);
+    let wallet_count = db::get_wallet_count_cached().await.unwrap_or(0);
     let total_claims = db::get_total_airdrops().await.unwrap_or(0);
 
     Json(json!({
@@ -105,64 +461,661 @@ pub async fn get_airdrop_stats() -> Json<serde_json::Value> {
     }))
 }
 
+// For monitoring: what the treasury holds of the project's mint and how many
+// more claims of the project's size that covers. Campaign mints aren't shown.
+async fn get_treasury_balance(project: Project) -> Result<Json<serde_json::Value>, AppError> {
+    let mint = match &project.token_mint {
+        Some(mint) => mint.clone(),
+        None => solana::default_mint()?,
+    };
+    let balance = solana::blocking(move || solana::treasury_balance(&mint)).await?;
+
+    let per_claim = solana::ui_to_raw(project.claim_tokens().max(0) as u64, balance.decimals)
+        .filter(|&raw| raw > 0);
+    Ok(Json(json!({
+        "mint": balance.mint,
+        "amount": balance.amount,
+        "ui_amount": balance.ui_amount,
+        "decimals": balance.decimals,
+        "claims_remaining": per_claim.map(|raw| balance.amount / raw)
+    })))
+}
+
 pub async fn get_referral_code(
+    project: Project,
     Query(params): Query<HashMap<String, String>>,
 ) -> Json<serde_json::Value> {
     if let Some(wallet) = params.get("wallet") {
-        match db::get_referral_code_by_wallet(wallet).await {
+        let wallet = solana::normalize_wallet(wallet);
+        match db::get_referral_code_by_wallet(&project.id, &wallet).await {
             Ok(code) => Json(json!({ "referral_code": code })),
-            Err(_) => Json(json!({ "error": "Wallet not found" })),
+            Err(_) => Json(json!({
+                "error": "Wallet not found",
+                "code": ErrorCode::WalletNotFound
+            })),
+        }
+    } else {
+        Json(json!({
+            "error": "Missing wallet parameter",
+            "code": ErrorCode::MissingParameter
+        }))
+    }
+}
+
+// Stable 0..100 bucket for a wallet, so its rollout status never flip-flops
+fn rollout_bucket(wallet: &str) -> u8 {
+    let digest = hash(wallet.as_bytes()).to_bytes();
+    let n = u64::from_le_bytes(digest[..8].try_into().unwrap());
+    (n % 100) as u8
+}
+
+// Whether a CLAIM_ROLLOUT_PERCENT of `percent` lets the wallet claim
+fn in_rollout(wallet: &str, percent: u8) -> bool {
+    rollout_bucket(wallet) < percent
+}
+
+// Store the airdrop's slot, block time and fee once the chain reports them.
+// Runs in the background; the claim has already succeeded, so failures are
+// only logged.
+fn record_confirmation(sig: String) {
+    tokio::spawn(async move {
+        let fetch_sig = sig.clone();
+        let confirmation =
+            match tokio::task::spawn_blocking(move || solana::fetch_confirmation(&fetch_sig)).await
+            {
+                Ok(Ok(confirmation)) => confirmation,
+                Ok(Err(e)) => {
+                    tracing::warn!(%sig, error = %e, "Could not fetch airdrop confirmation");
+                    return;
+                }
+                Err(e) => {
+                    tracing::warn!(%sig, error = %e, "Airdrop confirmation task failed");
+                    return;
+                }
+            };
+
+        if let Err(e) = db::set_airdrop_confirmation(&sig, &confirmation).await {
+            tracing::warn!(%sig, error = %e, "Failed to store airdrop confirmation");
+        }
+    });
+}
+
+// Payouts can be paused (e.g. while the treasury is topped up) without
+// stopping task completion or wallet connections. Starts from CLAIMS_PAUSED
+// and is toggled through the admin API.
+pub static CLAIMS_PAUSED: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(CONFIG.claims_paused));
+
+fn check_claims_open() -> Result<(), AppError> {
+    if CLAIMS_PAUSED.load(Ordering::Relaxed) {
+        return Err(AppError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Claims are temporarily paused",
+        )
+        .with_code(ErrorCode::ClaimsPaused));
+    }
+    Ok(())
+}
+
+async fn require_claims_open(req: Request, next: Next) -> Result<Response, AppError> {
+    check_claims_open()?;
+    Ok(next.run(req).await)
+}
+
+// Whether the wallet could claim right now, ignoring the fee, so the UI can
+// explain why the claim button is disabled
+async fn get_claim_eligibility(
+    project: Project,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let wallet = wallet_param(&params)?;
+
+    let user_info = db::get_user_info(&project.id, &wallet)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => AppError::new(StatusCode::NOT_FOUND, "Wallet not found")
+                .with_code(ErrorCode::WalletNotFound),
+            _ => AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "DB error")
+                .with_code(ErrorCode::DatabaseError),
+        })?;
+
+    let claims_paused = CLAIMS_PAUSED.load(Ordering::Relaxed);
+    Ok(Json(
+        match check_claims_open().and_then(|_| check_eligibility(&project, &wallet, &user_info)) {
+            Ok(()) => json!({ "eligible": true, "claims_paused": claims_paused }),
+            Err(e) => json!({
+                "eligible": false,
+                "claims_paused": claims_paused,
+                "reason": e.message,
+                "code": e.code
+            }),
+        },
+    ))
+}
+
+// Everything that has to hold before we look for a fee or send tokens
+fn check_eligibility(
+    project: &Project,
+    wallet: &str,
+    user_info: &UserInfo,
+) -> Result<(), AppError> {
+    let min_points = project.claim_points();
+    if CONFIG.bypass_points_check {
+        tracing::warn!(%wallet, "BYPASS_POINTS_CHECK active: skipping minimum points check");
+    } else if user_info.total_points < min_points {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            format!("Not enough points (min {})", min_points),
+        )
+        .with_code(ErrorCode::InsufficientPoints));
+    }
+
+    if !in_rollout(wallet, CONFIG.claim_rollout_percent) {
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            "Claiming is not yet available for this wallet",
+        )
+        .with_code(ErrorCode::ClaimNotYetAvailable));
+    }
+
+    Ok(())
+}
+
+pub async fn claim_airdrop(
+    project: Project,
+    Json(req): Json<ClaimRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let result = try_claim(project, req).await;
+    if let Err(e) = &result {
+        telemetry::record_claim_failed(e);
+    }
+    result
+}
+
+// The claim is what makes a referral count. The tokens are already out, so a
+// failure here is logged rather than failing the claim.
+async fn credit_referral(project: &str, wallet: &str) {
+    match db::credit_pending_referral(
+        project,
+        wallet,
+        CONFIG.referral_reward_points,
+        CONFIG.referral_tier2_points,
+        CONFIG.max_referral_points_per_user,
+    )
+    .await
+    {
+        Ok(Some((referrer_id, awarded))) if awarded < CONFIG.referral_reward_points => {
+            tracing::info!(
+                %referrer_id,
+                awarded,
+                "Referral award capped by MAX_REFERRAL_POINTS_PER_USER"
+            );
         }
+        Ok(_) => {}
+        Err(e) => tracing::error!(
+            wallet,
+            error = %e,
+            "Claim recorded but crediting the referrer failed"
+        ),
+    }
+}
+
+async fn try_claim(
+    project: Project,
+    req: ClaimRequest,
+) -> Result<Json<serde_json::Value>, AppError> {
+    solana::validate_wallet(&req.wallet_address)?;
+
+    let db_error = |_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "DB error")
+            .with_code(ErrorCode::DatabaseError)
+    };
+
+    // Proves the wallet owner started this claim, not someone who only knows
+    // the address. The nonce is single use, so a captured request can't be
+    // replayed.
+    if CONFIG.require_signed_claim {
+        let (Some(nonce), Some(signature)) = (req.nonce, req.signature.as_deref()) else {
+            return Err(AppError::new(
+                StatusCode::UNAUTHORIZED,
+                "Claim must be signed: request a nonce and include nonce and signature",
+            )
+            .with_code(ErrorCode::InvalidSignature));
+        };
+        auth::verify_wallet_signature(&req.wallet_address, &nonce, signature).await?;
+    }
+
+    let user_info = db::get_user_info_fresh(&project.id, &req.wallet_address)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => AppError::new(StatusCode::NOT_FOUND, "Wallet not found")
+                .with_code(ErrorCode::WalletNotFound),
+            _ => AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "DB error")
+                .with_code(ErrorCode::DatabaseError),
+        })?;
+
+    check_eligibility(&project, &req.wallet_address, &user_info)?;
+
+    // An earlier transfer may still land; sending another could pay out twice
+    if db::has_pending_airdrop(&project.id, &req.wallet_address)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            "A previous airdrop to this wallet is still awaiting confirmation",
+        )
+        .with_code(ErrorCode::ClaimPending));
+    }
+
+    // A project with its own mint always sends that. Otherwise the project's
+    // active campaign decides which token and how much, falling back to the
+    // project's amount (AIRDROP_TOKENS by default) of the default TOKEN_MINT.
+    // AIRDROP_PAYOUT then decides whether that amount or the wallet's points
+    // set the payout.
+    let (mint, fixed_tokens) = match &project.token_mint {
+        Some(mint) => (mint.clone(), project.claim_tokens()),
+        None => match db::get_active_campaign(&project.id)
+            .await
+            .map_err(db_error)?
+        {
+            Some(campaign) => (campaign.token_mint, campaign.airdrop_amount as i64),
+            None => (solana::default_mint()?, project.claim_tokens()),
+        },
+    };
+    let amount = project.compute_airdrop_amount(fixed_tokens, user_info.total_points);
+    // Checked before the fee is looked for, so a refused claim leaves it unused
+    if amount.tokens <= 0 {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Nothing to claim for this wallet's points",
+        )
+        .with_code(ErrorCode::InsufficientPoints));
+    }
+    let (tokens, claim_points) = (amount.tokens, amount.points);
+
+    // Partners may have a reduced fee; a zero override skips the fee scan entirely
+    let required_fee = db::get_fee_override(&project.id, &req.wallet_address)
+        .await
+        .map_err(db_error)?
+        .map(|l| l as u64)
+        .unwrap_or_else(|| project.fee_lamports());
+
+    let fee_tx = if required_fee == 0 {
+        None
     } else {
-        Json(json!({ "error": "Missing wallet parameter" }))
+        let fee_tx = solana::check_fee_paid(&req.wallet_address, required_fee)
+            .await?
+            .ok_or_else(|| {
+                AppError::new(StatusCode::PAYMENT_REQUIRED, "Fee not detected")
+                    .with_code(ErrorCode::FeeNotDetected)
+            })?;
+
+        // Check if already used
+        let fee_valid = db::record_fee_if_new(&req.wallet_address, &fee_tx)
+            .await
+            .map_err(db_error)?;
+        if !fee_valid {
+            return Err(
+                AppError::new(StatusCode::CONFLICT, "Fee already used for previous claim")
+                    .with_code(ErrorCode::FeeAlreadyUsed),
+            );
+        }
+        Some(fee_tx)
+    };
+
+    // Two claims can both get this far with the same fee, or with points for
+    // only one of them. Only the claim that takes the points and holds the
+    // fee may send.
+    let remaining_points = db::reserve_claim(
+        &project.id,
+        &req.wallet_address,
+        fee_tx.as_deref(),
+        claim_points,
+    )
+    .await
+    .map_err(|e| match e {
+        ClaimError::InsufficientPoints => AppError::new(
+            StatusCode::CONFLICT,
+            format!("Not enough points for this claim ({} needed)", claim_points),
+        )
+        .with_code(ErrorCode::InsufficientPoints),
+        ClaimError::FeeAlreadyUsed => AppError::new(
+            StatusCode::CONFLICT,
+            "Fee is already being used by another claim",
+        )
+        .with_code(ErrorCode::FeeAlreadyUsed),
+        ClaimError::Db(_) => AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "DB error")
+            .with_code(ErrorCode::DatabaseError),
+    })?;
+
+    let transfer = match solana::send_tokens(&req.wallet_address, &mint, tokens as u64).await {
+        Ok(transfer) => transfer,
+        Err(e) => {
+            // Nothing was sent; giving the points and fee back lets the
+            // wallet retry with the same fee
+            tracing::warn!(
+                wallet = %req.wallet_address,
+                fee_tx = fee_tx.as_deref().unwrap_or("none"),
+                error = %e,
+                "Transfer failed; points and fee released for retry"
+            );
+            if let Err(release_err) = db::release_claim(
+                &project.id,
+                &req.wallet_address,
+                fee_tx.as_deref(),
+                claim_points,
+            )
+            .await
+            {
+                tracing::error!(
+                    wallet = %req.wallet_address,
+                    fee_tx = fee_tx.as_deref().unwrap_or("none"),
+                    points = claim_points,
+                    error = %release_err,
+                    "Could not release the claim after a failed transfer"
+                );
+            }
+            return Err(e);
+        }
+    };
+    let sig = transfer.signature;
+
+    // Not confirmed in time, but it may still land. Parked for the reconciler,
+    // which records the claim if it does; the wallet can't claim again until
+    // then, and the fee stays held until we know either way.
+    if let solana::TransferStatus::Pending {
+        last_valid_block_height,
+    } = transfer.status
+    {
+        let pending = PendingAirdrop {
+            signature: sig.clone(),
+            project_id: project.id.clone(),
+            wallet_address: req.wallet_address.clone(),
+            mint: mint.clone(),
+            amount: tokens,
+            points: claim_points,
+            fee_tx: fee_tx.clone(),
+            last_valid_block_height: last_valid_block_height as i64,
+        };
+        db::insert_pending_airdrop(&pending).await.map_err(|e| {
+            tracing::error!(
+                wallet = %req.wallet_address,
+                tx = %sig,
+                fee_tx = fee_tx.as_deref().unwrap_or("none"),
+                tokens,
+                error = %e,
+                "Transfer unconfirmed and recording it as pending failed"
+            );
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Tokens were submitted but the claim could not be recorded",
+            )
+            .with_code(ErrorCode::DatabaseError)
+        })?;
+
+        tracing::warn!(wallet = %req.wallet_address, tx = %sig, "Transfer pending confirmation");
+        return Ok(Json(json!({
+            "status": "Airdrop submitted, awaiting confirmation",
+            "confirmation": transfer.status.as_str(),
+            "tokens": tokens,
+            "mint": mint,
+            "tx": sig
+        })));
     }
+
+    // Log airdrop + update DB in one go
+    db::finalize_claim(
+        &project.id,
+        &req.wallet_address,
+        tokens,
+        &sig,
+        fee_tx.as_deref(),
+        claim_points,
+    )
+    .await
+    .map_err(|e| {
+        // The tokens are already out; this is what's needed to reconcile
+        tracing::error!(
+            wallet = %req.wallet_address,
+            tx = %sig,
+            fee_tx = fee_tx.as_deref().unwrap_or("none"),
+            tokens,
+            error = %e,
+            "Transfer confirmed but recording the claim failed"
+        );
+        match e {
+            ClaimError::FeeAlreadyUsed => AppError::new(
+                StatusCode::CONFLICT,
+                "Tokens were sent but the fee had already been used by another claim",
+            )
+            .with_code(ErrorCode::FeeAlreadyUsed),
+            // The points were taken by reserve_claim, so finalize_claim
+            // doesn't refuse for those
+            ClaimError::InsufficientPoints | ClaimError::Db(_) => AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Tokens were sent but the claim could not be recorded",
+            )
+            .with_code(ErrorCode::DatabaseError),
+        }
+    })?;
+
+    claim_recorded(&project.id, &req.wallet_address, tokens, &mint, &sig).await;
+
+    Ok(Json(json!({
+        "status": "Airdrop sent",
+        "confirmation": transfer.status.as_str(),
+        "tokens": tokens,
+        "mint": mint,
+        "tx": sig,
+        "remaining_points": remaining_points
+    })))
 }
 
-pub async fn claim_airdrop(Json(req): Json<ClaimRequest>) -> Json<serde_json::Value> {
-    let user_info = db::get_user_info(&req.wallet_address).await.unwrap();
+// Follow-up once a claim is recorded, whether by the claim itself or by the
+// reconciler for a transfer that confirmed late
+pub async fn claim_recorded(project: &str, wallet: &str, tokens: i64, mint: &str, sig: &str) {
+    telemetry::record_airdrop_sent(project, tokens);
+
+    credit_referral(project, wallet).await;
 
-    if user_info.total_points < 1000 {
-        return Json(json!({ "error": "Not enough points (min 1000)" }));
+    if CONFIG.record_confirmations {
+        record_confirmation(sig.to_string());
     }
 
-    let paid_sig = solana::check_fee_paid(&req.wallet_address).await.unwrap();
-if paid_sig.is_none() {
-    return Json(json!({ "error": "Fee not detected" }));
+    notify::publish(Event::AirdropClaimed {
+        project: project.to_string(),
+        wallet: wallet.to_string(),
+        amount: tokens,
+        mint: mint.to_string(),
+        tx: sig.to_string(),
+    });
 }
 
-let fee_tx = paid_sig.unwrap();
+pub async fn get_nonce(
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let wallet = wallet_param(&params)?;
+
+    let challenge = auth::issue_nonce(&wallet).await?;
 
-// Check if already used
-let fee_valid = db::record_fee_if_new(&req.wallet_address, &fee_tx).await.unwrap();
-if !fee_valid {
-    return Json(json!({ "error": "Fee already used for previous claim" }));
+    Ok(Json(json!(challenge)))
 }
 
- //   if !paid {
-   //     return Json(json!({ "error": "Fee not detected" }));
-    //}
+fn validate_display_name(name: &str) -> Result<(), AppError> {
+    let len = name.chars().count();
+    if !(DISPLAY_NAME_MIN_LEN..=DISPLAY_NAME_MAX_LEN).contains(&len) {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Display name must be {}-{} characters",
+                DISPLAY_NAME_MIN_LEN, DISPLAY_NAME_MAX_LEN
+            ),
+        )
+        .with_code(ErrorCode::InvalidDisplayName));
+    }
 
-    // Send exactly 1000 tokens
-    match solana::send_tokens(&req.wallet_address, 1000).await {
-        Ok(sig) => {
-            // Log airdrop + update DB
-            db::log_airdrop(&req.wallet_address, 1000, &sig)
-                .await
-                .unwrap();
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Display name may only contain letters, numbers, '_' and '-'",
+        )
+        .with_code(ErrorCode::InvalidDisplayName));
+    }
 
-            db::deduct_user_points(&req.wallet_address, 1000)
+    let lower = name.to_ascii_lowercase();
+    if BLOCKED_NAME_WORDS.iter().any(|w| lower.contains(w)) {
+        return Err(
+            AppError::new(StatusCode::BAD_REQUEST, "Display name is not allowed")
+                .with_code(ErrorCode::InvalidDisplayName),
+        );
+    }
+
+    Ok(())
+}
+
+async fn set_display_name(
+    project: Project,
+    Json(req): Json<DisplayNameRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let name = req.display_name.trim();
+    validate_display_name(name)?;
+
+    auth::verify_wallet_signature(&req.wallet_address, &req.nonce, &req.signature).await?;
+
+    let updated = db::set_display_name(&project.id, &req.wallet_address, name)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db_err) if db_err.code().as_deref() == Some("23505") => {
+                AppError::new(StatusCode::CONFLICT, "Display name already taken")
+                    .with_code(ErrorCode::DisplayNameTaken)
+            }
+            _ => AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to set display name",
+            )
+            .with_code(ErrorCode::DatabaseError),
+        })?;
+
+    if !updated {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Wallet not found")
+            .with_code(ErrorCode::WalletNotFound));
+    }
+
+    Ok(Json(
+        json!({ "status": "display name set", "display_name": name }),
+    ))
+}
+
+// Blank strings clear a field
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+// Contact details for claim receipts. Signed like the display name so nobody
+// can redirect someone else's receipts.
+async fn set_contact(
+    project: Project,
+    Json(req): Json<ContactRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let email = non_empty(&req.email);
+    let webhook_url = non_empty(&req.webhook_url);
+
+    if email.is_some_and(|e| !e.contains('@') || e.chars().any(char::is_whitespace)) {
+        return Err(
+            AppError::new(StatusCode::BAD_REQUEST, "Invalid email address")
+                .with_code(ErrorCode::InvalidRequest),
+        );
+    }
+    if webhook_url.is_some_and(|u| !u.starts_with("https://")) {
+        return Err(
+            AppError::new(StatusCode::BAD_REQUEST, "Webhook URL must use https")
+                .with_code(ErrorCode::InvalidRequest),
+        );
+    }
+
+    auth::verify_wallet_signature(&req.wallet_address, &req.nonce, &req.signature).await?;
+
+    let updated = db::set_contact(
+        &project.id,
+        &req.wallet_address,
+        email,
+        webhook_url,
+        req.notify_on_claim,
+    )
+    .await
+    .map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to save contact")
+            .with_code(ErrorCode::DatabaseError)
+    })?;
+
+    if !updated {
+        return Err(AppError::new(StatusCode::NOT_FOUND, "Wallet not found")
+            .with_code(ErrorCode::WalletNotFound));
+    }
+
+    Ok(Json(json!({
+        "status": "contact saved",
+        "email": email,
+        "webhook_url": webhook_url,
+        "notify_on_claim": req.notify_on_claim
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{block_on, random_wallet, user_with_points};
+
+    async fn points_response(wallet: &str) -> serde_json::Value {
+        let project = db::get_project("default").await.unwrap().unwrap();
+        let params = HashMap::from([("wallet".to_string(), wallet.to_string())]);
+        let Json(body) = get_points(project, Query(params)).await.unwrap();
+        body
+    }
+
+    #[test]
+    fn the_points_response_says_whether_the_wallet_claimed() {
+        block_on(async {
+            let wallet = user_with_points(0).await;
+            assert_eq!(points_response(&wallet).await["has_claimed"], false);
+
+            let mut conn = db::DB_POOL.acquire().await.unwrap();
+            db::set_claimed(&mut conn, "default", &wallet)
                 .await
                 .unwrap();
+            assert_eq!(points_response(&wallet).await["has_claimed"], true);
+        });
+    }
+
+    #[test]
+    fn no_wallet_is_in_a_zero_percent_rollout() {
+        assert!((0..1_000).all(|_| !in_rollout(&random_wallet(), 0)));
+    }
 
-            db::set_claimed(&req.wallet_address).await.unwrap(); // still needed
-	    db::mark_fee_used(&req.wallet_address, &fee_tx).await.unwrap();
+    #[test]
+    fn every_wallet_is_in_a_full_rollout() {
+        assert!((0..1_000).all(|_| in_rollout(&random_wallet(), 100)));
+    }
 
-            Json(json!({
-                "status": "Airdrop sent",
-                "tokens": 1000,
-                "tx": sig
-            }))
+    #[test]
+    fn a_wallet_joins_at_the_percent_just_above_its_bucket() {
+        for _ in 0..1_000 {
+            let wallet = random_wallet();
+            let bucket = rollout_bucket(&wallet);
+            assert!(bucket < 100);
+            assert!(!in_rollout(&wallet, bucket));
+            assert!(in_rollout(&wallet, bucket + 1));
         }
-        Err(e) => Json(json!({ "error": e.to_string() })),
+    }
+
+    #[test]
+    fn a_partial_rollout_admits_about_that_share() {
+        let admitted = (0..2_000)
+            .filter(|_| in_rollout(&random_wallet(), 25))
+            .count();
+        assert!((400..600).contains(&admitted), "{admitted} of 2000");
     }
 }
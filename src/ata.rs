@@ -0,0 +1,88 @@
+use crate::{config::CONFIG, db, solana};
+use std::{collections::HashMap, time::Duration};
+
+// Wallets are retried this many times before the worker gives up on them;
+// the claim still creates the account itself
+const MAX_ATTEMPTS: i32 = 5;
+// Wallets handled per tick
+const BATCH_SIZE: i64 = 20;
+
+// Background job creating token accounts for queued wallets (PRECREATE_ATAS),
+// so their claim doesn't have to. One wallet at a time, each holding an
+// RPC_PERMITS permit, so it never crowds out claims.
+pub fn start() {
+    if !CONFIG.precreate_atas {
+        return;
+    }
+    let interval = Duration::from_millis(CONFIG.ata_worker_interval_ms);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = run_batch().await {
+                tracing::warn!(error = %e, "ATA pre-creation batch failed");
+            }
+        }
+    });
+}
+
+async fn run_batch() -> Result<(), sqlx::Error> {
+    let queued = db::get_pending_ata_creations(MAX_ATTEMPTS, BATCH_SIZE).await?;
+    // Each project's mint is looked up once per batch
+    let mut mints: HashMap<String, Option<String>> = HashMap::new();
+
+    for pending in queued {
+        let (project, wallet) = (pending.project_id, pending.wallet_address);
+        let mint = match mints.get(&project) {
+            Some(mint) => mint.clone(),
+            None => {
+                let mint = project_mint(&project).await?;
+                mints.insert(project.clone(), mint.clone());
+                mint
+            }
+        };
+        let Some(mint) = mint else {
+            continue;
+        };
+
+        let Ok(_permit) = solana::RPC_PERMITS.acquire().await else {
+            return Ok(());
+        };
+
+        let (w, m) = (wallet.clone(), mint.clone());
+        let result = tokio::task::spawn_blocking(move || solana::ensure_ata(&w, &m))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r.map_err(|e| e.to_string()));
+
+        match result {
+            Ok(_) => db::complete_ata_creation(&project, &wallet).await?,
+            Err(e) => {
+                tracing::warn!(%wallet, %project, error = %e, "Failed to pre-create ATA");
+                db::fail_ata_creation(&project, &wallet, &e).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Same mint the project's claims would send; None (logged) when there is none
+async fn project_mint(project: &str) -> Result<Option<String>, sqlx::Error> {
+    let Some(project) = db::get_project(project).await? else {
+        return Ok(None);
+    };
+    if let Some(mint) = project.token_mint {
+        return Ok(Some(mint));
+    }
+    if let Some(campaign) = db::get_active_campaign(&project.id).await? {
+        return Ok(Some(campaign.token_mint));
+    }
+    match solana::default_mint() {
+        Ok(mint) => Ok(Some(mint)),
+        Err(e) => {
+            tracing::warn!(project = %project.id, error = %e, "ATA pre-creation skipped: no mint");
+            Ok(None)
+        }
+    }
+}
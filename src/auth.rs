@@ -0,0 +1,188 @@
+use crate::{
+    config::CONFIG,
+    db,
+    error::{AppError, ErrorCode},
+    models::auth::SignInChallenge,
+    solana,
+};
+use axum::http::StatusCode;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use solana_sdk::signature::Signature;
+use std::str::FromStr;
+use uuid::Uuid;
+
+const NONCE_TTL_SECONDS: i64 = 300;
+
+// Human-readable, domain-bound sign-in message. It is always rebuilt on the
+// server from the stored nonce, never taken from the client.
+pub fn sign_in_message(wallet: &str, nonce: &Uuid, issued_at: DateTime<Utc>) -> String {
+    let expires_at = issued_at + Duration::seconds(NONCE_TTL_SECONDS);
+    format!(
+        "{} wants you to sign in with wallet {}.\n\nNonce: {}\nIssued At: {}\nExpires At: {}",
+        CONFIG.auth_domain,
+        wallet,
+        nonce,
+        issued_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        expires_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+    )
+}
+
+// Issue a fresh single-use nonce for the wallet along with the exact message
+// it has to sign
+pub async fn issue_nonce(wallet: &str) -> Result<SignInChallenge, AppError> {
+    solana::validate_wallet(wallet)?;
+
+    let record = db::create_auth_nonce(wallet).await.map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to issue nonce")
+            .with_code(ErrorCode::DatabaseError)
+    })?;
+
+    Ok(SignInChallenge {
+        nonce: record.nonce,
+        message: sign_in_message(wallet, &record.nonce, record.created_at),
+        expires_at: record.created_at + Duration::seconds(NONCE_TTL_SECONDS),
+    })
+}
+
+// Check that `signature` (base58) is the wallet's signature over the sign-in
+// message for `nonce`, then burn the nonce so it can't be replayed
+pub async fn verify_wallet_signature(
+    wallet: &str,
+    nonce: &Uuid,
+    signature: &str,
+) -> Result<(), AppError> {
+    let pubkey = solana::validate_wallet(wallet)?;
+
+    let sig = Signature::from_str(signature).map_err(|_| {
+        AppError::new(StatusCode::BAD_REQUEST, "Invalid signature encoding")
+            .with_code(ErrorCode::InvalidSignature)
+    })?;
+
+    let record = db::get_auth_nonce(nonce)
+        .await
+        .map_err(|_| {
+            AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Nonce lookup failed")
+                .with_code(ErrorCode::DatabaseError)
+        })?
+        .filter(|r| r.wallet_address == wallet && !r.used)
+        .ok_or_else(|| {
+            AppError::new(StatusCode::UNAUTHORIZED, "Nonce expired or already used")
+                .with_code(ErrorCode::NonceExpired)
+        })?;
+
+    if Utc::now() > record.created_at + Duration::seconds(NONCE_TTL_SECONDS) {
+        return Err(
+            AppError::new(StatusCode::UNAUTHORIZED, "Nonce expired or already used")
+                .with_code(ErrorCode::NonceExpired),
+        );
+    }
+
+    let message = sign_in_message(wallet, nonce, record.created_at);
+    if !sig.verify(pubkey.as_ref(), message.as_bytes()) {
+        return Err(
+            AppError::new(StatusCode::UNAUTHORIZED, "Signature verification failed")
+                .with_code(ErrorCode::InvalidSignature),
+        );
+    }
+
+    // Guards against two requests racing with the same nonce
+    let consumed = db::consume_auth_nonce(nonce).await.map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Nonce lookup failed")
+            .with_code(ErrorCode::DatabaseError)
+    })?;
+
+    if !consumed {
+        return Err(
+            AppError::new(StatusCode::UNAUTHORIZED, "Nonce expired or already used")
+                .with_code(ErrorCode::NonceExpired),
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::block_on;
+    use solana_sdk::signature::{Keypair, Signer};
+
+    #[test]
+    fn a_signed_nonce_verifies_once() {
+        block_on(async {
+            let owner = Keypair::new();
+            let wallet = owner.pubkey().to_string();
+            let challenge = issue_nonce(&wallet).await.unwrap();
+            let signed = owner.sign_message(challenge.message.as_bytes()).to_string();
+
+            verify_wallet_signature(&wallet, &challenge.nonce, &signed)
+                .await
+                .unwrap();
+
+            let replay = verify_wallet_signature(&wallet, &challenge.nonce, &signed)
+                .await
+                .unwrap_err();
+            assert_eq!(replay.status, StatusCode::UNAUTHORIZED);
+            assert_eq!(replay.code, Some(ErrorCode::NonceExpired));
+        });
+    }
+
+    #[test]
+    fn a_signature_by_another_key_is_refused() {
+        block_on(async {
+            let wallet = Keypair::new().pubkey().to_string();
+            let challenge = issue_nonce(&wallet).await.unwrap();
+            let forged = Keypair::new().sign_message(challenge.message.as_bytes());
+
+            let err = verify_wallet_signature(&wallet, &challenge.nonce, &forged.to_string())
+                .await
+                .unwrap_err();
+            assert_eq!(err.status, StatusCode::UNAUTHORIZED);
+            assert_eq!(err.code, Some(ErrorCode::InvalidSignature));
+        });
+    }
+
+    #[test]
+    fn an_expired_nonce_is_refused() {
+        block_on(async {
+            let owner = Keypair::new();
+            let wallet = owner.pubkey().to_string();
+            let challenge = issue_nonce(&wallet).await.unwrap();
+            let issued_at = Utc::now() - Duration::seconds(NONCE_TTL_SECONDS + 1);
+            sqlx::query!(
+                "UPDATE auth_nonces SET created_at = $1 WHERE nonce = $2",
+                issued_at,
+                challenge.nonce
+            )
+            .execute(&*db::DB_POOL)
+            .await
+            .unwrap();
+
+            // Signed over the message as it reads for the backdated nonce
+            let message = sign_in_message(&wallet, &challenge.nonce, issued_at);
+            let signed = owner.sign_message(message.as_bytes());
+            let err = verify_wallet_signature(&wallet, &challenge.nonce, &signed.to_string())
+                .await
+                .unwrap_err();
+            assert_eq!(err.status, StatusCode::UNAUTHORIZED);
+            assert_eq!(err.code, Some(ErrorCode::NonceExpired));
+        });
+    }
+
+    #[test]
+    fn a_nonce_issued_to_another_wallet_is_refused() {
+        block_on(async {
+            // An attacker signs their own challenge and submits it with the
+            // victim's address, as a forged signed claim would
+            let (attacker, victim) = (Keypair::new(), Keypair::new().pubkey().to_string());
+            let challenge = issue_nonce(&attacker.pubkey().to_string()).await.unwrap();
+            let signed = attacker.sign_message(challenge.message.as_bytes());
+
+            let err = verify_wallet_signature(&victim, &challenge.nonce, &signed.to_string())
+                .await
+                .unwrap_err();
+            assert_eq!(err.status, StatusCode::UNAUTHORIZED);
+            assert_eq!(err.code, Some(ErrorCode::NonceExpired));
+        });
+    }
+}
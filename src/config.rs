@@ -1,5 +1,590 @@
 use dotenvy::dotenv;
-use std::env;
+use once_cell::sync::Lazy;
+use std::{env, str::FromStr};
+
+pub const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+pub const BIND_ADDR: &str = "0.0.0.0:8080";
+const DEFAULT_REQUIRED_FEE_LAMPORTS: u64 = 6_000; // 0.000006 SOL
+const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 1024;
+const DEFAULT_WEBHOOK_BATCH_WINDOW_MS: u64 = 2_000;
+const DEFAULT_DB_STARTUP_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_AIRDROP_TOKENS: i64 = 1_000;
+const DEFAULT_MIN_CLAIM_POINTS: i32 = 1_000;
+const DEFAULT_NOTIFIERS: &str = "sse,webhook,receipt";
+const DEFAULT_WALLET_COUNT_REFRESH_MS: u64 = 30_000;
+const DEFAULT_MAX_TASK_POINTS: i32 = 10_000;
+const DEFAULT_POINTS_SYNC_INTERVAL_MS: u64 = 5_000;
+const DEFAULT_POINTS_LOG_BATCH_SIZE: usize = 500;
+const DEFAULT_POINTS_LOG_FLUSH_MS: u64 = 1_000;
+const DEFAULT_RPC_MAX_CONCURRENCY: usize = 16;
+const DEFAULT_ATA_WORKER_INTERVAL_MS: u64 = 10_000;
+const DEFAULT_REFERRAL_MAX_DEPTH: i32 = 64;
+const DEFAULT_REFERRAL_REWARD_POINTS: i32 = 100;
+const DEFAULT_TASK_RATE_LIMIT_PER_MINUTE: u32 = 10;
+const DEFAULT_SSE_REPLAY_SIZE: usize = 20;
+const DEFAULT_FEE_SCAN_MAX_SIGNATURES: usize = 500;
+const DEFAULT_RPC_MAX_RETRIES: u32 = 3;
+const DEFAULT_TRANSFER_CONFIRM_TIMEOUT_SECS: u64 = 60;
+const DEFAULT_RECONCILE_INTERVAL_MS: u64 = 30_000;
+const DEFAULT_RPC_RETRY_BASE_DELAY_MS: u64 = 200;
+const DEFAULT_BLOCKHASH_RETRY_LIMIT: u32 = 3;
+// Largest SSE_REPLAY_SIZE accepted; every new subscriber is sent the whole replay
+pub const MAX_SSE_REPLAY_SIZE: usize = 200;
+
+pub static CONFIG: Lazy<Config> = Lazy::new(Config::from_env);
+
+pub struct Config {
+    pub required_fee_lamports: u64,
+    pub max_concurrent_requests: usize,
+    // /ready also requires the Solana RPC to answer, not just the database
+    pub ready_check_rpc: bool,
+    // complete_task calls allowed per wallet per minute (0 = unlimited), and
+    // optionally per client IP
+    pub task_rate_limit_per_minute: u32,
+    pub task_rate_limit_per_ip: Option<u32>,
+    // Upper bound on any user's total_points; None means uncapped
+    pub max_user_points: Option<i32>,
+    // Largest reward an admin can put on a single task
+    pub max_task_points: i32,
+    // Points a referrer earns per referee
+    pub referral_reward_points: i32,
+    // Lifetime cap on points a single referrer can earn from referrals
+    pub max_referral_points_per_user: Option<i32>,
+    // Bonus for the referrer's referrer on each new referral; 0 disables the
+    // second tier
+    pub referral_tier2_points: i32,
+    // Reject referrals that would make a wallet its own (indirect) referrer
+    pub referral_cycle_check: bool,
+    // How far up the referrer chain the cycle check looks
+    pub referral_max_depth: i32,
+    // Domain named in the wallet sign-in message
+    pub auth_domain: String,
+    // Where airdrop notifications are POSTed (e.g. a Discord webhook)
+    pub webhook_url: Option<String>,
+    // Notifications arriving within this window go out as one request
+    pub webhook_batch_window_ms: u64,
+    // Share of wallets (by stable hash bucket) allowed to claim, 0-100
+    pub claim_rollout_percent: u8,
+    // Fee payments older than this are ignored; None accepts any age
+    pub fee_max_age_seconds: Option<u64>,
+    // Slots a fee payment must be behind the tip before it counts; 0 accepts
+    // any confirmed payment
+    pub fee_min_confirmations: u64,
+    // How far back, in airdrop wallet signatures, a claim looks for its fee
+    pub fee_scan_max_signatures: usize,
+    // APP_ENV=production
+    pub production: bool,
+    // Browser origins allowed by CORS, e.g. "https://app.example"; None
+    // allows any origin, which production refuses
+    pub cors_allowed_origins: Option<Vec<String>>,
+    // Mount /api/dev/* endpoints; never allowed in production
+    pub dev_endpoints: bool,
+    // Tokio runtime sizing; None keeps tokio's defaults (one worker per CPU, 512 blocking)
+    pub worker_threads: Option<usize>,
+    pub max_blocking_threads: Option<usize>,
+    // Send opted-in users a receipt after a successful claim
+    pub notify_on_claim: bool,
+    // HTTP email provider for receipts: POSTed {from, to, subject, text}
+    // with the key as a bearer token
+    pub email_api_url: Option<String>,
+    pub email_api_key: Option<String>,
+    pub email_from: String,
+    // Sent as X-API-Version on every response; defaults to the crate version
+    pub api_version: String,
+    // Accumulate task points on completed_tasks and fold them into
+    // users.total_points periodically instead of on every completion
+    pub points_batch_sync: bool,
+    pub points_sync_interval_ms: u64,
+    // Buffer points ledger entries and write them in batches after the
+    // balance change commits, instead of inline on every transaction
+    pub points_log_async: bool,
+    pub points_log_batch_size: usize,
+    pub points_log_flush_ms: u64,
+    // Simulate each token transfer before submitting it
+    pub simulate_transfers: bool,
+    // Priority fee on our transfers and ATA creations, in micro-lamports per
+    // compute unit; 0 sends them without one
+    pub priority_fee_microlamports: u64,
+    // Compute unit limit requested alongside; None keeps the runtime default
+    pub compute_unit_limit: Option<u32>,
+    // How long a claim waits for its transfer to confirm before parking it in
+    // pending_airdrops for the reconciler
+    pub transfer_confirm_timeout_secs: u64,
+    // How often the reconciler checks pending transfers
+    pub reconcile_interval_ms: u64,
+    // Upper bound on concurrent transfers and background RPC jobs
+    pub rpc_max_concurrency: usize,
+    // Extra attempts for an RPC read that failed transiently (timeouts, 429s,
+    // 5xx), and the delay before the first one; later ones back off
+    pub rpc_max_retries: u32,
+    pub rpc_retry_base_delay_ms: u64,
+    // Extra attempts for a transfer whose blockhash expired before it landed
+    pub blockhash_retry_limit: u32,
+    // Create token accounts for wallets that complete tasks before they claim
+    pub precreate_atas: bool,
+    pub ata_worker_interval_ms: u64,
+    // Fetch each airdrop's slot, block time and fee after it confirms and
+    // store them on airdrop_log
+    pub record_confirmations: bool,
+    // CLUSTER, e.g. "devnet" or "mainnet-beta"
+    pub cluster: Option<String>,
+    // Claims must carry a signed sign-in nonce, like display name changes
+    pub require_signed_claim: bool,
+    // Reject connect_wallet unless it carries a signed nonce
+    pub require_signed_connect: bool,
+    // Closed beta: only wallets in beta_allowlist may connect
+    pub beta_allowlist: bool,
+    // Start with claims paused; admins can toggle it at runtime
+    pub claims_paused: bool,
+    // QA only: let wallets claim without the minimum points
+    pub bypass_points_check: bool,
+    // How often the cached wallet count is re-read from the users table
+    pub wallet_count_refresh_ms: u64,
+    // Transports claim/task events are sent to; see notify.rs
+    pub notifiers: Vec<String>,
+    // Tokens per claim when no campaign is active, in UI units (not base units)
+    pub airdrop_tokens: i64,
+    // How a claim's tokens and points are worked out; see
+    // Project::compute_airdrop_amount
+    pub airdrop_payout: AirdropPayout,
+    // Tokens per point under AIRDROP_PAYOUT=per_point, in UI units
+    pub airdrop_tokens_per_point: i64,
+    // Points a wallet needs before it can claim, unless its project sets its own
+    pub min_claim_points: i32,
+    // How long startup keeps retrying an unreachable database, in seconds
+    pub db_startup_timeout_secs: u64,
+    // How long a SIGTERM/SIGINT waits for in-flight requests (claims mid
+    // transfer especially) before exiting anyway, in seconds
+    pub shutdown_timeout_secs: u64,
+    // Cache-Control max-age (seconds) for /api/tasks and the public stats
+    // endpoints; 0 sends no-store like every other route
+    pub cache_tasks_max_age: u64,
+    pub cache_stats_max_age: u64,
+    // Recent airdrop events sent to new SSE subscribers before the live
+    // stream; 0 disables the replay
+    pub sse_replay_size: usize,
+    // Serve several projects, picked per request by X-Project-Id or by
+    // subdomain of PROJECT_DOMAIN; off means everything is 'default'
+    pub multi_tenant: bool,
+    pub project_domain: Option<String>,
+    // How wallets appear in public (unauthenticated) responses
+    pub public_wallet_display: PublicWalletDisplay,
+    // ALLOWED_MINTS: the only mints the treasury may send. None (unset)
+    // disables the check.
+    pub allowed_mints: Option<Vec<String>>,
+    // MaxMind country database; geoblocking is off without it
+    pub geoip_db_path: Option<String>,
+    // ISO 3166-1 alpha-2 codes (uppercased) whose IPs may not claim
+    pub blocked_countries: Vec<String>,
+    // Take the client IP from X-Forwarded-For; only safe behind a proxy that
+    // sets it
+    pub trust_forwarded_for: bool,
+    // Proxies in front of the app, each appending to X-Forwarded-For. The
+    // client is this many entries from the right; anything further left was
+    // sent by the client and can't be trusted.
+    pub trusted_proxy_hops: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AirdropPayout {
+    // The project's (or campaign's) token amount for MIN_CLAIM_POINTS points
+    Fixed,
+    // The wallet's whole balance, at AIRDROP_TOKENS_PER_POINT tokens a point
+    PerPoint,
+}
+
+impl FromStr for AirdropPayout {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "fixed" => Ok(AirdropPayout::Fixed),
+            "per_point" => Ok(AirdropPayout::PerPoint),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PublicWalletDisplay {
+    Truncated,
+    Full,
+}
+
+impl FromStr for PublicWalletDisplay {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "truncated" => Ok(PublicWalletDisplay::Truncated),
+            "full" => Ok(PublicWalletDisplay::Full),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Config {
+    fn from_env() -> Self {
+        let config = Config {
+            required_fee_lamports: required_fee_lamports(),
+            max_concurrent_requests: env_or(
+                "MAX_CONCURRENT_REQUESTS",
+                DEFAULT_MAX_CONCURRENT_REQUESTS,
+            ),
+            task_rate_limit_per_minute: env_or(
+                "TASK_RATE_LIMIT_PER_MINUTE",
+                DEFAULT_TASK_RATE_LIMIT_PER_MINUTE,
+            ),
+            task_rate_limit_per_ip: env_opt("TASK_RATE_LIMIT_PER_IP"),
+            ready_check_rpc: env_or("READY_CHECK_RPC", false),
+            max_user_points: env_opt("MAX_USER_POINTS"),
+            max_task_points: env_or("MAX_TASK_POINTS", DEFAULT_MAX_TASK_POINTS),
+            referral_reward_points: env_or(
+                "REFERRAL_REWARD_POINTS",
+                DEFAULT_REFERRAL_REWARD_POINTS,
+            ),
+            max_referral_points_per_user: env_opt("MAX_REFERRAL_POINTS_PER_USER"),
+            referral_tier2_points: env_or("REFERRAL_TIER2_POINTS", 0),
+            referral_cycle_check: env_or("REFERRAL_CYCLE_CHECK", true),
+            referral_max_depth: env_or("REFERRAL_MAX_DEPTH", DEFAULT_REFERRAL_MAX_DEPTH),
+            auth_domain: env::var("AUTH_DOMAIN").unwrap_or_else(|_| "ecocoin.app".to_string()),
+            webhook_url: env_opt("WEBHOOK_URL"),
+            webhook_batch_window_ms: env_or(
+                "WEBHOOK_BATCH_WINDOW_MS",
+                DEFAULT_WEBHOOK_BATCH_WINDOW_MS,
+            ),
+            claim_rollout_percent: env_or("CLAIM_ROLLOUT_PERCENT", 100),
+            fee_max_age_seconds: env_opt("FEE_MAX_AGE_SECONDS"),
+            fee_min_confirmations: env_or("FEE_MIN_CONFIRMATIONS", 0),
+            fee_scan_max_signatures: env_or(
+                "FEE_SCAN_MAX_SIGNATURES",
+                DEFAULT_FEE_SCAN_MAX_SIGNATURES,
+            ),
+            production: env::var("APP_ENV").is_ok_and(|v| v.eq_ignore_ascii_case("production")),
+            dev_endpoints: env_or("DEV_ENDPOINTS", false),
+            worker_threads: env_opt("WORKER_THREADS"),
+            max_blocking_threads: env_opt("MAX_BLOCKING_THREADS"),
+            notify_on_claim: env_or("NOTIFY_ON_CLAIM", false),
+            email_api_url: env_opt("EMAIL_API_URL"),
+            email_api_key: env_opt("EMAIL_API_KEY"),
+            email_from: env::var("EMAIL_FROM")
+                .unwrap_or_else(|_| "no-reply@ecocoin.app".to_string()),
+            api_version: env::var("API_VERSION")
+                .unwrap_or_else(|_| env!("CARGO_PKG_VERSION").to_string()),
+            points_batch_sync: env_or("POINTS_BATCH_SYNC", false),
+            points_sync_interval_ms: env_or(
+                "POINTS_SYNC_INTERVAL_MS",
+                DEFAULT_POINTS_SYNC_INTERVAL_MS,
+            ),
+            points_log_async: env_or("POINTS_LOG_ASYNC", false),
+            points_log_batch_size: env_or("POINTS_LOG_BATCH_SIZE", DEFAULT_POINTS_LOG_BATCH_SIZE),
+            points_log_flush_ms: env_or("POINTS_LOG_FLUSH_MS", DEFAULT_POINTS_LOG_FLUSH_MS),
+            simulate_transfers: env_or("SIMULATE_TRANSFERS", false),
+            priority_fee_microlamports: env_or("PRIORITY_FEE_MICROLAMPORTS", 0),
+            compute_unit_limit: env_opt("COMPUTE_UNIT_LIMIT"),
+            transfer_confirm_timeout_secs: env_or(
+                "TRANSFER_CONFIRM_TIMEOUT_SECS",
+                DEFAULT_TRANSFER_CONFIRM_TIMEOUT_SECS,
+            ),
+            reconcile_interval_ms: env_or("RECONCILE_INTERVAL_MS", DEFAULT_RECONCILE_INTERVAL_MS),
+            rpc_max_concurrency: env_or("RPC_MAX_CONCURRENCY", DEFAULT_RPC_MAX_CONCURRENCY),
+            rpc_max_retries: env_or("RPC_MAX_RETRIES", DEFAULT_RPC_MAX_RETRIES),
+            rpc_retry_base_delay_ms: env_or(
+                "RPC_RETRY_BASE_DELAY_MS",
+                DEFAULT_RPC_RETRY_BASE_DELAY_MS,
+            ),
+            blockhash_retry_limit: env_or("BLOCKHASH_RETRY_LIMIT", DEFAULT_BLOCKHASH_RETRY_LIMIT),
+            precreate_atas: env_or("PRECREATE_ATAS", false),
+            ata_worker_interval_ms: env_or(
+                "ATA_WORKER_INTERVAL_MS",
+                DEFAULT_ATA_WORKER_INTERVAL_MS,
+            ),
+            record_confirmations: env_or("RECORD_CONFIRMATIONS", true),
+            cluster: env_opt("CLUSTER"),
+            require_signed_claim: env_or("REQUIRE_SIGNED_CLAIM", false),
+            require_signed_connect: env_or("REQUIRE_SIGNED_CONNECT", false),
+            beta_allowlist: env_or("BETA_ALLOWLIST", false),
+            claims_paused: env_or("CLAIMS_PAUSED", false),
+            bypass_points_check: env_or("BYPASS_POINTS_CHECK", false),
+            wallet_count_refresh_ms: env_or(
+                "WALLET_COUNT_REFRESH_MS",
+                DEFAULT_WALLET_COUNT_REFRESH_MS,
+            ),
+            notifiers: env::var("NOTIFIERS")
+                .unwrap_or_else(|_| DEFAULT_NOTIFIERS.to_string())
+                .split(',')
+                .map(|n| n.trim().to_ascii_lowercase())
+                .filter(|n| !n.is_empty())
+                .collect(),
+            airdrop_tokens: env_or("AIRDROP_TOKENS", DEFAULT_AIRDROP_TOKENS),
+            airdrop_payout: env_or("AIRDROP_PAYOUT", AirdropPayout::Fixed),
+            airdrop_tokens_per_point: env_or("AIRDROP_TOKENS_PER_POINT", 1),
+            min_claim_points: env_or("MIN_CLAIM_POINTS", DEFAULT_MIN_CLAIM_POINTS),
+            db_startup_timeout_secs: env_or("DB_STARTUP_TIMEOUT", DEFAULT_DB_STARTUP_TIMEOUT_SECS),
+            shutdown_timeout_secs: env_or("SHUTDOWN_TIMEOUT", DEFAULT_SHUTDOWN_TIMEOUT_SECS),
+            cache_tasks_max_age: env_or("CACHE_TASKS_MAX_AGE", 30),
+            cache_stats_max_age: env_or("CACHE_STATS_MAX_AGE", 30),
+            sse_replay_size: env_or("SSE_REPLAY_SIZE", DEFAULT_SSE_REPLAY_SIZE),
+            multi_tenant: env_or("MULTI_TENANT", false),
+            project_domain: env::var("PROJECT_DOMAIN")
+                .ok()
+                .map(|d| d.trim().trim_start_matches('.').to_ascii_lowercase())
+                .filter(|d| !d.is_empty()),
+            public_wallet_display: env_or("PUBLIC_WALLET_DISPLAY", PublicWalletDisplay::Truncated),
+            cors_allowed_origins: env::var("CORS_ALLOWED_ORIGINS").ok().map(|v| {
+                v.split(',')
+                    .map(|o| o.trim().trim_end_matches('/').to_string())
+                    .filter(|o| !o.is_empty())
+                    .collect()
+            }),
+            allowed_mints: env::var("ALLOWED_MINTS").ok().map(|v| {
+                v.split(',')
+                    .map(|m| m.trim().to_string())
+                    .filter(|m| !m.is_empty())
+                    .collect()
+            }),
+            geoip_db_path: env_opt("GEOIP_DB_PATH"),
+            blocked_countries: env::var("BLOCKED_COUNTRIES")
+                .unwrap_or_default()
+                .split(',')
+                .map(|c| c.trim().to_ascii_uppercase())
+                .filter(|c| !c.is_empty())
+                .collect(),
+            trust_forwarded_for: env_or("TRUST_FORWARDED_FOR", false),
+            trusted_proxy_hops: env_or("TRUSTED_PROXY_HOPS", 1),
+        };
+
+        assert!(
+            config.max_concurrent_requests > 0,
+            "MAX_CONCURRENT_REQUESTS must be greater than 0"
+        );
+        assert!(
+            config.max_user_points.is_none_or(|cap| cap >= 0),
+            "MAX_USER_POINTS must be non-negative"
+        );
+        assert!(
+            config
+                .max_referral_points_per_user
+                .is_none_or(|cap| cap >= 0),
+            "MAX_REFERRAL_POINTS_PER_USER must be non-negative"
+        );
+        assert!(
+            config.fee_scan_max_signatures > 0,
+            "FEE_SCAN_MAX_SIGNATURES must be greater than 0"
+        );
+        assert!(
+            config.referral_reward_points >= 0,
+            "REFERRAL_REWARD_POINTS must be non-negative"
+        );
+        assert!(
+            config.referral_tier2_points >= 0,
+            "REFERRAL_TIER2_POINTS must be non-negative"
+        );
+        assert!(
+            config.claim_rollout_percent <= 100,
+            "CLAIM_ROLLOUT_PERCENT must be between 0 and 100"
+        );
+        assert!(
+            !config.production || config.cors_allowed_origins.is_some(),
+            "CORS_ALLOWED_ORIGINS is required when APP_ENV=production"
+        );
+        for origin in config.cors_allowed_origins.iter().flatten() {
+            // An origin is scheme://host[:port], nothing more
+            let valid = reqwest::Url::parse(origin).is_ok_and(|u| {
+                matches!(u.scheme(), "http" | "https")
+                    && u.host_str().is_some()
+                    && u.path() == "/"
+                    && u.query().is_none()
+            });
+            assert!(valid, "Invalid origin in CORS_ALLOWED_ORIGINS: {}", origin);
+        }
+        assert!(
+            !(config.production && config.dev_endpoints),
+            "DEV_ENDPOINTS cannot be enabled when APP_ENV=production"
+        );
+        assert!(
+            !config.api_version.is_empty()
+                && config.api_version.chars().all(|c| c.is_ascii_graphic()),
+            "API_VERSION must be non-empty printable ASCII"
+        );
+        assert!(
+            config.points_sync_interval_ms > 0,
+            "POINTS_SYNC_INTERVAL_MS must be greater than 0"
+        );
+        assert!(
+            config.rpc_max_concurrency > 0,
+            "RPC_MAX_CONCURRENCY must be greater than 0"
+        );
+        assert!(
+            config.compute_unit_limit.is_none_or(|l| l > 0),
+            "COMPUTE_UNIT_LIMIT must be greater than 0"
+        );
+        assert!(
+            config.transfer_confirm_timeout_secs > 0,
+            "TRANSFER_CONFIRM_TIMEOUT_SECS must be greater than 0"
+        );
+        assert!(
+            config.reconcile_interval_ms > 0,
+            "RECONCILE_INTERVAL_MS must be greater than 0"
+        );
+        assert!(
+            config.rpc_retry_base_delay_ms > 0,
+            "RPC_RETRY_BASE_DELAY_MS must be greater than 0"
+        );
+        assert!(
+            config.ata_worker_interval_ms > 0,
+            "ATA_WORKER_INTERVAL_MS must be greater than 0"
+        );
+        assert!(
+            config.points_log_batch_size > 0 && config.points_log_flush_ms > 0,
+            "POINTS_LOG_BATCH_SIZE and POINTS_LOG_FLUSH_MS must be greater than 0"
+        );
+        assert!(
+            !(config.bypass_points_check && (config.production || config.is_mainnet())),
+            "BYPASS_POINTS_CHECK cannot be enabled on mainnet or in production"
+        );
+        assert!(
+            config.sse_replay_size <= MAX_SSE_REPLAY_SIZE,
+            "SSE_REPLAY_SIZE must be at most {}",
+            MAX_SSE_REPLAY_SIZE
+        );
+        assert!(
+            config.task_rate_limit_per_ip != Some(0),
+            "TASK_RATE_LIMIT_PER_IP must be greater than 0 (unset it to disable)"
+        );
+        assert!(
+            config.referral_max_depth > 0,
+            "REFERRAL_MAX_DEPTH must be greater than 0"
+        );
+        assert!(
+            config.wallet_count_refresh_ms > 0,
+            "WALLET_COUNT_REFRESH_MS must be greater than 0"
+        );
+        assert!(
+            config.airdrop_tokens > 0,
+            "AIRDROP_TOKENS must be greater than 0"
+        );
+        assert!(
+            config.airdrop_tokens_per_point > 0,
+            "AIRDROP_TOKENS_PER_POINT must be greater than 0"
+        );
+        // Zero would let every connected wallet claim
+        assert!(
+            config.min_claim_points > 0,
+            "MIN_CLAIM_POINTS must be greater than 0"
+        );
+        if let (Some(allowed), Ok(mint)) = (&config.allowed_mints, env::var("TOKEN_MINT")) {
+            assert!(
+                allowed.iter().any(|m| *m == mint.trim()),
+                "TOKEN_MINT {} is not in ALLOWED_MINTS",
+                mint.trim()
+            );
+        }
+        assert!(
+            config.blocked_countries.is_empty() || config.geoip_db_path.is_some(),
+            "BLOCKED_COUNTRIES requires GEOIP_DB_PATH"
+        );
+        assert!(
+            config.trusted_proxy_hops > 0,
+            "TRUSTED_PROXY_HOPS must be greater than 0"
+        );
+        assert!(
+            config.worker_threads != Some(0),
+            "WORKER_THREADS must be greater than 0"
+        );
+        assert!(
+            config.max_blocking_threads != Some(0),
+            "MAX_BLOCKING_THREADS must be greater than 0"
+        );
+
+        config
+    }
+
+    // One info line with the settings that matter when diagnosing a
+    // deployment. URLs are reduced to their host and keys to set/unset, so
+    // this is safe to ship to log aggregators.
+    pub fn log_summary(&self, mint_decimals: Option<u8>) {
+        let on_off = |b: bool| if b { "on" } else { "off" };
+        let set_unset = |o: bool| if o { "set" } else { "unset" };
+
+        tracing::info!(
+            bind = BIND_ADDR,
+            cluster = self.cluster.as_deref().unwrap_or("unset"),
+            mainnet = self.is_mainnet(),
+            rpc_host = %env::var("SOLANA_RPC_URL").ok().as_deref().map_or("unset".to_string(), url_host),
+            token_mint = env::var("TOKEN_MINT").as_deref().unwrap_or("unset"),
+            mint_decimals = ?mint_decimals,
+            fee_sol = %lamports_to_sol(self.required_fee_lamports),
+            fee_lamports = self.required_fee_lamports,
+            fee_min_confirmations = self.fee_min_confirmations,
+            fee_max_age_seconds = ?self.fee_max_age_seconds,
+            fee_scan_max_signatures = self.fee_scan_max_signatures,
+            min_claim_points = self.min_claim_points,
+            referral_reward_points = self.referral_reward_points,
+            airdrop_tokens = self.airdrop_tokens,
+            airdrop_payout = ?self.airdrop_payout,
+            airdrop_tokens_per_point = self.airdrop_tokens_per_point,
+            claim_rollout_percent = self.claim_rollout_percent,
+            production = self.production,
+            cors_origins = %self.cors_allowed_origins.as_ref().map_or("any".to_string(), |o| o.join(",")),
+            multi_tenant = on_off(self.multi_tenant),
+            claims_paused = on_off(self.claims_paused),
+            beta_allowlist = on_off(self.beta_allowlist),
+            require_signed_claim = on_off(self.require_signed_claim),
+            require_signed_connect = on_off(self.require_signed_connect),
+            dev_endpoints = on_off(self.dev_endpoints),
+            bypass_points_check = on_off(self.bypass_points_check),
+            points_batch_sync = on_off(self.points_batch_sync),
+            points_log_async = on_off(self.points_log_async),
+            simulate_transfers = on_off(self.simulate_transfers),
+            priority_fee_microlamports = self.priority_fee_microlamports,
+            compute_unit_limit = ?self.compute_unit_limit,
+            transfer_confirm_timeout_secs = self.transfer_confirm_timeout_secs,
+            record_confirmations = on_off(self.record_confirmations),
+            precreate_atas = on_off(self.precreate_atas),
+            rpc_max_concurrency = self.rpc_max_concurrency,
+            rpc_max_retries = self.rpc_max_retries,
+            blockhash_retry_limit = self.blockhash_retry_limit,
+            ready_check_rpc = on_off(self.ready_check_rpc),
+            task_rate_limit_per_minute = self.task_rate_limit_per_minute,
+            task_rate_limit_per_ip = ?self.task_rate_limit_per_ip,
+            notify_on_claim = on_off(self.notify_on_claim),
+            notifiers = %self.notifiers.join(","),
+            sse_replay_size = self.sse_replay_size,
+            allowed_mints = %self.allowed_mints.as_ref().map_or("any".to_string(), |m| m.join(",")),
+            geoip = set_unset(self.geoip_db_path.is_some()),
+            blocked_countries = %self.blocked_countries.join(","),
+            trust_forwarded_for = on_off(self.trust_forwarded_for),
+            trusted_proxy_hops = self.trusted_proxy_hops,
+            webhook = %self.webhook_url.as_deref().map_or("unset".to_string(), url_host),
+            email_api_key = set_unset(self.email_api_key.is_some()),
+            // Only where the key comes from, never the key
+            airdrop_wallet = if env::var("AIR_DROP_WALLET_KEY").is_ok() {
+                "key"
+            } else if env::var("AIR_DROP_WALLET_PATH").is_ok() {
+                "file"
+            } else {
+                "unset"
+            },
+            fee_payer_wallet = set_unset(env::var("FEE_PAYER_WALLET_PATH").is_ok()),
+            read_replica = set_unset(env::var("DATABASE_READ_URL").is_ok()),
+            api_version = %self.api_version,
+            "Resolved configuration"
+        );
+    }
+
+    // Treat the deployment as mainnet if CLUSTER says so, or when CLUSTER is
+    // unset and the RPC URL points at mainnet, to err on the side of caution
+    pub fn is_mainnet(&self) -> bool {
+        match &self.cluster {
+            Some(cluster) => cluster.to_ascii_lowercase().contains("mainnet"),
+            None => env::var("SOLANA_RPC_URL")
+                .map(|url| url.to_ascii_lowercase().contains("mainnet"))
+                .unwrap_or(false),
+        }
+    }
+}
 
 pub fn load_env() {
     dotenv().ok();
@@ -9,3 +594,94 @@ pub fn load_env() {
 pub fn get_env(key: &str) -> String {
     env::var(key).expect(&format!("Missing env var: {}", key))
 }
+
+// Host part of a URL, dropping any path, query or credentials that may carry
+// an API key
+fn url_host(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+        .unwrap_or_else(|| "invalid".to_string())
+}
+
+// Parse an optional env var, panicking on a malformed value rather than
+// silently falling back to the default
+fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    match env::var(key) {
+        Ok(v) => v
+            .trim()
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid {}: {}", key, v)),
+        Err(_) => default,
+    }
+}
+
+fn env_opt<T: FromStr>(key: &str) -> Option<T> {
+    env::var(key).ok().map(|v| {
+        v.trim()
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid {}: {}", key, v))
+    })
+}
+
+// Fee can be given either as REQUIRED_FEE_LAMPORTS (exact) or REQUIRED_FEE_SOL
+// (decimal, e.g. "0.006"), but not both
+fn required_fee_lamports() -> u64 {
+    match (
+        env::var("REQUIRED_FEE_LAMPORTS").ok(),
+        env::var("REQUIRED_FEE_SOL").ok(),
+    ) {
+        (Some(_), Some(_)) => {
+            panic!("Set only one of REQUIRED_FEE_LAMPORTS or REQUIRED_FEE_SOL")
+        }
+        (Some(lamports), None) => lamports
+            .trim()
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid REQUIRED_FEE_LAMPORTS: {}", lamports)),
+        (None, Some(sol)) => sol_to_lamports(&sol)
+            .unwrap_or_else(|e| panic!("Invalid REQUIRED_FEE_SOL {:?}: {}", sol, e)),
+        (None, None) => DEFAULT_REQUIRED_FEE_LAMPORTS,
+    }
+}
+
+// Exact decimal SOL -> lamports conversion, no floating point involved
+pub fn sol_to_lamports(sol: &str) -> Result<u64, String> {
+    let sol = sol.trim();
+    let (whole, frac) = sol.split_once('.').unwrap_or((sol, ""));
+
+    if whole.is_empty() && frac.is_empty() {
+        return Err("empty amount".into());
+    }
+    if !whole
+        .chars()
+        .chain(frac.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+        return Err("expected a non-negative decimal number".into());
+    }
+    if frac.len() > 9 {
+        return Err("more than 9 decimal places".into());
+    }
+
+    let whole: u64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().map_err(|_| "amount too large".to_string())?
+    };
+    let frac: u64 = format!("{:0<9}", frac).parse().unwrap_or(0);
+
+    whole
+        .checked_mul(LAMPORTS_PER_SOL)
+        .and_then(|l| l.checked_add(frac))
+        .ok_or_else(|| "amount too large".to_string())
+}
+
+pub fn lamports_to_sol(lamports: u64) -> String {
+    let frac = format!("{:09}", lamports % LAMPORTS_PER_SOL);
+    let frac = frac.trim_end_matches('0');
+    if frac.is_empty() {
+        format!("{}", lamports / LAMPORTS_PER_SOL)
+    } else {
+        format!("{}.{}", lamports / LAMPORTS_PER_SOL, frac)
+    }
+}
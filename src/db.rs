@@ -1,8 +1,30 @@
-use crate::models::{task::Task, user::UserInfo};
+use crate::config::CONFIG;
+use crate::models::{
+    airdrop::{PendingAirdrop, PendingAtaCreation},
+    auth::AuthNonce,
+    campaign::Campaign,
+    leaderboard::{PointsLeaderboardEntry, ReferralLeaderboardEntry},
+    project::Project,
+    stats::PointsTotals,
+    task::{
+        CompletedTask, Task, TaskCompletion, TaskError, TaskSeed, TaskSubmission, TaskVerification,
+    },
+    user::{
+        public_wallet, truncate_wallet, ClaimContact, ClaimError, PointHistoryEntry, ReferralLink,
+        UserInfo,
+    },
+};
+use crate::points_log::{Ledger, PointEvent};
+use crate::solana::TxConfirmation;
 use once_cell::sync::Lazy;
-use sqlx::{postgres::PgPoolOptions, PgPool};
+use sqlx::{postgres::PgPoolOptions, PgConnection, PgPool};
+use std::sync::{
+    atomic::{AtomicI64, Ordering},
+    Mutex,
+};
 use uuid::Uuid;
 
+// Primary pool; every write and anything that must see its own writes
 pub static DB_POOL: Lazy<PgPool> = Lazy::new(|| {
     let url = std::env::var("DATABASE_URL").expect("Missing DATABASE_URL");
     PgPoolOptions::new()
@@ -11,140 +33,548 @@ pub static DB_POOL: Lazy<PgPool> = Lazy::new(|| {
         .expect("Failed to create DB pool")
 });
 
+// Read-only queries (leaderboards, stats, profile reads) go to the replica at
+// DATABASE_READ_URL so they don't compete with claims. Without one it is just
+// the primary pool. Replica reads may lag slightly behind writes.
+pub static READ_POOL: Lazy<PgPool> = Lazy::new(|| match std::env::var("DATABASE_READ_URL") {
+    Ok(url) => PgPoolOptions::new()
+        .max_connections(5)
+        .connect_lazy(&url)
+        .expect("Failed to create read DB pool"),
+    Err(_) => DB_POOL.clone(),
+});
+
 pub async fn init_db() -> Result<(), sqlx::Error> {
+    wait_for_db().await?;
+
     sqlx::migrate!()
         .run(&*DB_POOL)
         .await
-        .map_err(|e| sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
+        .map_err(|e| sqlx::Error::Io(std::io::Error::other(e)))?;
+
+    seed_tasks().await?;
+    Ok(())
+}
+
+// Postgres often comes up a little after the app (e.g. under docker-compose),
+// so keep retrying the first connection with backoff until DB_STARTUP_TIMEOUT
+async fn wait_for_db() -> Result<(), sqlx::Error> {
+    let timeout = std::time::Duration::from_secs(CONFIG.db_startup_timeout_secs);
+    let started = std::time::Instant::now();
+    let mut delay = std::time::Duration::from_millis(250);
+    let mut attempt = 1;
+
+    loop {
+        // The pool's own acquire timeout is far longer than one attempt should take
+        let attempt_timeout = std::time::Duration::from_secs(5)
+            .min(timeout.saturating_sub(started.elapsed()))
+            .max(std::time::Duration::from_millis(100));
+        let conn = tokio::time::timeout(attempt_timeout, DB_POOL.acquire())
+            .await
+            .unwrap_or(Err(sqlx::Error::PoolTimedOut));
+
+        match conn {
+            Ok(_) => return Ok(()),
+            Err(e) if started.elapsed() + delay < timeout => {
+                tracing::warn!(attempt, error = %e, retry_in_ms = delay.as_millis() as u64, "Database not reachable yet");
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(std::time::Duration::from_secs(5));
+                attempt += 1;
+            }
+            Err(e) => {
+                tracing::error!(attempt, error = %e, "Giving up on the database after {}s (DB_STARTUP_TIMEOUT)", timeout.as_secs());
+                return Err(e);
+            }
+        }
+    }
+}
+
+// TASKS_SEED_JSON is either an inline JSON array or a path to a file holding one
+fn load_task_seed() -> Result<Option<Vec<TaskSeed>>, sqlx::Error> {
+    let Ok(value) = std::env::var("TASKS_SEED_JSON") else {
+        return Ok(None);
+    };
+
+    let json = if value.trim_start().starts_with('[') {
+        value
+    } else {
+        std::fs::read_to_string(value.trim()).map_err(sqlx::Error::Io)?
+    };
+
+    serde_json::from_str(&json)
+        .map(Some)
+        .map_err(|e| sqlx::Error::Configuration(format!("Invalid TASKS_SEED_JSON: {}", e).into()))
+}
+
+// Insert the seed tasks, but only into an empty table so edited tasks are never touched
+async fn seed_tasks() -> Result<(), sqlx::Error> {
+    let Some(seed) = load_task_seed()? else {
+        return Ok(());
+    };
+
+    let mut tx = DB_POOL.begin().await?;
+
+    // Keeps two instances booting at once from both seeing an empty table
+    sqlx::query!("LOCK TABLE tasks IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut *tx)
+        .await?;
+
+    let existing = sqlx::query!("SELECT COUNT(*) as count FROM tasks")
+        .fetch_one(&mut *tx)
+        .await?
+        .count
+        .unwrap_or(0);
+
+    if existing > 0 {
+        return Ok(());
+    }
+
+    for task in &seed {
+        sqlx::query!(
+            "INSERT INTO tasks (name, points, description, category)
+             VALUES ($1, $2, $3, COALESCE($4, 'general'))",
+            task.name,
+            task.points,
+            task.description,
+            task.category
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    tracing::info!(count = seed.len(), "Seeded tasks from TASKS_SEED_JSON");
     Ok(())
 }
 
-// Create user if not exists
-pub async fn create_user(wallet: &str) -> Result<Uuid, sqlx::Error> {
+// Create user in the project if not exists
+pub async fn create_user(project: &str, wallet: &str) -> Result<Uuid, sqlx::Error> {
     let result = sqlx::query!(
-        "INSERT INTO users (wallet_address) 
-         VALUES ($1) 
-         ON CONFLICT(wallet_address) DO NOTHING 
+        "INSERT INTO users (project_id, wallet_address)
+         VALUES ($1, $2)
+         ON CONFLICT (project_id, wallet_address) DO NOTHING
          RETURNING id",
+        project,
         wallet
     )
     .fetch_optional(&*DB_POOL)
     .await?;
 
     if let Some(record) = result {
+        // Only once loaded; an unloaded cache gets the real count on first read
+        let _ = WALLET_COUNT.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |c| {
+            (c >= 0).then_some(c + 1)
+        });
         Ok(record.id)
     } else {
-        let existing = sqlx::query!("SELECT id FROM users WHERE wallet_address = $1", wallet)
-            .fetch_one(&*DB_POOL)
-            .await?;
+        let existing = sqlx::query!(
+            "SELECT id FROM users WHERE project_id = $1 AND wallet_address = $2",
+            project,
+            wallet
+        )
+        .fetch_one(&*DB_POOL)
+        .await?;
         Ok(existing.id)
     }
 }
 
-// Lookup user by referral code (wallet or UUID)
-pub async fn get_user_id_by_referral_code(code: &str) -> Result<Option<Uuid>, sqlx::Error> {
+// Lookup user by referral code (wallet or UUID) within the project
+pub async fn get_user_id_by_referral_code(
+    project: &str,
+    code: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
     if let Ok(uuid) = Uuid::parse_str(code) {
-        let res = sqlx::query!("SELECT id FROM users WHERE referral_code = $1", uuid)
-            .fetch_optional(&*DB_POOL)
-            .await?;
+        let res = sqlx::query!(
+            "SELECT id FROM users WHERE project_id = $1 AND referral_code = $2",
+            project,
+            uuid
+        )
+        .fetch_optional(&*DB_POOL)
+        .await?;
         return Ok(res.map(|r| r.id));
     }
 
-    let res = sqlx::query!("SELECT id FROM users WHERE wallet_address = $1", code)
+    get_user_id_by_wallet(project, code).await
+}
+
+pub async fn get_user_id_by_wallet(
+    project: &str,
+    wallet: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let res = sqlx::query!(
+        "SELECT id FROM users WHERE project_id = $1 AND wallet_address = $2",
+        project,
+        wallet
+    )
+    .fetch_optional(&*DB_POOL)
+    .await?;
+    Ok(res.map(|r| r.id))
+}
+
+pub async fn get_wallet_by_user_id(user_id: &Uuid) -> Result<Option<String>, sqlx::Error> {
+    let res = sqlx::query!("SELECT wallet_address FROM users WHERE id = $1", user_id)
         .fetch_optional(&*DB_POOL)
         .await?;
-    Ok(res.map(|r| r.id))
+    Ok(res.map(|r| r.wallet_address))
 }
 
-// Set referral
-pub async fn set_referrer(user_id: &Uuid, referrer_id: &Uuid) -> Result<(), sqlx::Error> {
-    sqlx::query!(
+// Whether `candidate` appears in the referrer chain above `of` (or is `of`
+// itself), walking at most `max_depth` links. Linking `candidate` under `of`
+// would then close a loop.
+async fn is_ancestor(
+    conn: &mut PgConnection,
+    candidate: &Uuid,
+    of: &Uuid,
+    max_depth: i32,
+) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        r#"WITH RECURSIVE chain (id, referrer_id, depth) AS (
+               SELECT id, referrer_id, 0 FROM users WHERE id = $2
+               UNION ALL
+               SELECT u.id, u.referrer_id, c.depth + 1
+               FROM users u
+               JOIN chain c ON u.id = c.referrer_id
+               WHERE c.depth < $3
+           )
+           SELECT EXISTS (SELECT 1 FROM chain WHERE id = $1) AS "found!""#,
+        candidate,
+        of,
+        max_depth
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res.found)
+}
+
+// Link `user_id` under `referrer_id` unless it already has a referrer, would
+// refer itself, or (when `cycle_depth` is set) the link would close a loop
+// within that many levels. The referrer's points stay pending until the
+// referee claims; see credit_pending_referral.
+pub async fn set_referrer(
+    user_id: &Uuid,
+    referrer_id: &Uuid,
+    cycle_depth: Option<i32>,
+) -> Result<ReferralLink, sqlx::Error> {
+    if user_id == referrer_id {
+        return Ok(ReferralLink::SelfReferral);
+    }
+
+    let mut tx = DB_POOL.begin().await?;
+
+    // Two links can each look fine alone and still form a loop together
+    // (A->B and B->A at once), so linking is serialised
+    sqlx::query!("SELECT pg_advisory_xact_lock(hashtext('set_referrer'))")
+        .execute(&mut *tx)
+        .await?;
+
+    if let Some(depth) = cycle_depth {
+        if is_ancestor(&mut tx, user_id, referrer_id, depth).await? {
+            return Ok(ReferralLink::Cycle);
+        }
+    }
+
+    let updated = sqlx::query!(
         "UPDATE users SET referrer_id = $1 WHERE id = $2 AND referrer_id IS NULL",
         referrer_id,
         user_id
     )
-    .execute(&*DB_POOL)
-    .await?;
-    Ok(())
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    tx.commit().await?;
+
+    Ok(if updated == 1 {
+        ReferralLink::Linked
+    } else {
+        ReferralLink::AlreadyReferred
+    })
 }
 
-// Add referral points to referrer
-pub async fn add_referral_points(referrer_id: &Uuid) -> Result<(), sqlx::Error> {
-    sqlx::query!(
-        "UPDATE users SET total_points = total_points + 100 WHERE id = $1",
-        referrer_id
+// Pay out a referee's pending referral, once, after their first claim:
+// `points` to their referrer and, when `tier2_points` is non-zero, that
+// smaller bonus to the referrer's own referrer, in one transaction. Each stops
+// once that user's lifetime referral points (both tiers, per the points log)
+// reach `cap`. Returns the referrer and the points they actually got (less
+// than `points` when the cap cut in), or None if there was nothing pending.
+pub async fn credit_pending_referral(
+    project: &str,
+    wallet: &str,
+    points: i32,
+    tier2_points: i32,
+    cap: Option<i32>,
+) -> Result<Option<(Uuid, i32)>, sqlx::Error> {
+    let mut tx = DB_POOL.begin().await?;
+
+    // Flipping the flag first makes a second claim, or a concurrent one,
+    // find nothing pending
+    let Some(pending) = sqlx::query!(
+        r#"UPDATE users SET referral_credited = TRUE
+           WHERE project_id = $1 AND wallet_address = $2
+             AND referrer_id IS NOT NULL AND NOT referral_credited
+           RETURNING id, referrer_id as "referrer_id!""#,
+        project,
+        wallet
     )
-    .execute(&*DB_POOL)
-    .await?;
-    Ok(())
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(None);
+    };
+    let (referee_id, referrer_id) = (&pending.id, &pending.referrer_id);
+    let referee = referee_id.to_string();
+
+    let award = credit_referral(&mut tx, referrer_id, points, "referral", &referee, cap).await?;
+
+    if tier2_points > 0 {
+        let grand_referrer =
+            sqlx::query!("SELECT referrer_id FROM users WHERE id = $1", referrer_id)
+                .fetch_one(&mut *tx)
+                .await?
+                .referrer_id;
+        // set_referrer keeps chains acyclic; this only guards against a loop
+        // that predates that check
+        if let Some(grand) = grand_referrer.filter(|g| g != referee_id && g != referrer_id) {
+            credit_referral(
+                &mut tx,
+                &grand,
+                tier2_points,
+                "referral_tier2",
+                &referee,
+                cap,
+            )
+            .await?;
+        }
+    }
+
+    tx.commit().await?;
+    Ok(Some((*referrer_id, award)))
 }
 
-// Complete task
-pub async fn complete_task(wallet: &str, task_id: Uuid) -> Result<(), sqlx::Error> {
-    let user = sqlx::query!(
-        "SELECT id, has_claimed FROM users WHERE wallet_address = $1",
-        wallet
+// Add up to `points` to one user for a referral, limited by `cap`, and log
+// it. Returns what was credited.
+async fn credit_referral(
+    conn: &mut PgConnection,
+    user_id: &Uuid,
+    points: i32,
+    reason: &str,
+    referee: &str,
+    cap: Option<i32>,
+) -> Result<i32, sqlx::Error> {
+    // Serialises concurrent referrals crediting the same user. Rows are
+    // always locked child before parent, so two tiers can't deadlock.
+    sqlx::query!("SELECT id FROM users WHERE id = $1 FOR UPDATE", user_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let earned = sqlx::query!(
+        "SELECT COALESCE(SUM(delta), 0)::INT as earned FROM point_events
+         WHERE user_id = $1 AND reason IN ('referral', 'referral_tier2')",
+        user_id
     )
-    .fetch_one(&*DB_POOL)
-    .await?;
+    .fetch_one(&mut *conn)
+    .await?
+    .earned
+    .unwrap_or(0);
+
+    let award = match cap {
+        Some(cap) => points.min(cap - earned).max(0),
+        None => points,
+    };
 
-    // If the user has already claimed, they can't earn more from tasks
-    if user.has_claimed.unwrap_or(false) {
-        return Err(sqlx::Error::RowNotFound); // or create a custom error later
+    if award > 0 {
+        sqlx::query!(
+            "UPDATE users SET total_points = total_points + $1 WHERE id = $2",
+            award,
+            user_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        // Always written inline: the cap above is read back from the ledger
+        record_point_event(conn, user_id, award, reason, Some(referee)).await?;
     }
 
-    // Check if task is already completed
-    let exists = sqlx::query!(
-        "SELECT 1 as exists FROM completed_tasks WHERE user_id = $1 AND task_id = $2",
-        user.id,
+    Ok(award)
+}
+
+async fn task_completed(user_id: &Uuid, task_id: Uuid) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"SELECT EXISTS(
+             SELECT 1 FROM completed_tasks WHERE user_id = $1 AND task_id = $2
+         ) as "completed!""#,
+        user_id,
         task_id
     )
-    .fetch_optional(&*DB_POOL)
+    .fetch_one(&*DB_POOL)
     .await?;
+    Ok(row.completed)
+}
+
+// Complete task; returns the points awarded. Tasks of other projects are
+// TaskNotFound.
+pub async fn complete_task(
+    project: &str,
+    wallet: &str,
+    task_id: Uuid,
+) -> Result<TaskCompletion, TaskError> {
+    let user = sqlx::query!(
+        "SELECT id, has_claimed FROM users WHERE project_id = $1 AND wallet_address = $2",
+        project,
+        wallet
+    )
+    .fetch_optional(&*DB_POOL)
+    .await?
+    .ok_or(TaskError::UserNotFound)?;
+
+    let task = sqlx::query!(
+        "SELECT points, post_claim_allowed FROM tasks
+         WHERE id = $1 AND project_id = $2 AND is_active",
+        task_id,
+        project
+    )
+    .fetch_optional(&*DB_POOL)
+    .await?
+    .ok_or(TaskError::TaskNotFound)?;
 
-    if exists.is_some() {
-        return Err(sqlx::Error::RowNotFound);
+    // Check if task is already completed
+    if task_completed(&user.id, task_id).await? {
+        return Err(TaskError::AlreadyCompleted);
     }
 
-    let task = sqlx::query!("SELECT points FROM tasks WHERE id = $1", task_id)
-        .fetch_one(&*DB_POOL)
-        .await?;
+    // If the user has already claimed, they can't earn more from tasks. Tasks
+    // flagged post_claim_allowed can still be completed, for zero points.
+    let claimed = user.has_claimed.unwrap_or(false);
+    if claimed && !task.post_claim_allowed {
+        return Err(TaskError::AlreadyClaimed);
+    }
+    let points = if claimed { 0 } else { task.points };
 
-    // Record task completion
-    sqlx::query!(
-        "INSERT INTO completed_tasks (user_id, task_id) VALUES ($1, $2)",
+    let mut tx = DB_POOL.begin().await?;
+    let mut ledger = Ledger::default();
+
+    // Record task completion. In batch mode the points stay on the row until
+    // the next sync instead of touching the users row now.
+    let batched = CONFIG.points_batch_sync;
+    let inserted = sqlx::query!(
+        "INSERT INTO completed_tasks (user_id, task_id, points_awarded, synced, project_id)
+         VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (user_id, task_id) DO NOTHING",
         user.id,
-        task_id
+        task_id,
+        points,
+        !batched,
+        project
     )
-    .execute(&*DB_POOL)
-    .await?;
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    // A concurrent request completed it between the check and the insert
+    if inserted == 0 {
+        return Err(TaskError::AlreadyCompleted);
+    }
 
-    // ✅ Add task points ONLY if user hasn't claimed
-    sqlx::query!(
-        "UPDATE users SET total_points = total_points + $1 WHERE id = $2",
-        task.points,
+    if points > 0 {
+        if !batched {
+            // ✅ Add task points ONLY if user hasn't claimed
+            sqlx::query!(
+                "UPDATE users SET total_points = total_points + $1 WHERE id = $2",
+                points,
+                user.id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        ledger
+            .record(
+                &mut tx,
+                PointEvent {
+                    user_id: user.id,
+                    delta: points,
+                    reason: "task".to_string(),
+                    reference_id: Some(task_id.to_string()),
+                },
+            )
+            .await?;
+    }
+
+    // Counted like fetch_user_info, including task points awaiting a sync
+    let total_points = sqlx::query!(
+        r#"SELECT (COALESCE(u.total_points, 0) + COALESCE(
+               (SELECT SUM(points_awarded) FROM completed_tasks
+                WHERE user_id = u.id AND NOT synced), 0))::INT as "total!"
+           FROM users u WHERE u.id = $1"#,
         user.id
     )
-    .execute(&*DB_POOL)
-    .await?;
+    .fetch_one(&mut *tx)
+    .await?
+    .total;
 
-    Ok(())
+    tx.commit().await?;
+    ledger.commit();
+
+    Ok(TaskCompletion {
+        points_awarded: points,
+        total_points,
+    })
+}
+
+// A wallet's completed tasks with their names, newest first. Tasks retired
+// since are still listed.
+pub async fn get_completed_tasks(
+    project: &str,
+    wallet: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<CompletedTask>, sqlx::Error> {
+    sqlx::query_as!(
+        CompletedTask,
+        "SELECT t.id, t.name, c.points_awarded as points, c.completed_at
+         FROM completed_tasks c
+         JOIN users u ON u.id = c.user_id
+         JOIN tasks t ON t.id = c.task_id
+         WHERE u.project_id = $1 AND u.wallet_address = $2
+         ORDER BY c.completed_at DESC NULLS LAST, t.name
+         LIMIT $3 OFFSET $4",
+        project,
+        wallet,
+        limit,
+        offset
+    )
+    .fetch_all(&*READ_POOL)
+    .await
 }
 
-// Fetch user points + completed tasks + referral count
-pub async fn get_user_info(wallet: &str) -> Result<UserInfo, sqlx::Error> {
+// Fetch user points + completed tasks + referral count, from the read pool
+pub async fn get_user_info(project: &str, wallet: &str) -> Result<UserInfo, sqlx::Error> {
+    fetch_user_info(&READ_POOL, project, wallet).await
+}
+
+// Same as get_user_info but from the primary, for decisions (like claiming)
+// that can't act on a lagging replica
+pub async fn get_user_info_fresh(project: &str, wallet: &str) -> Result<UserInfo, sqlx::Error> {
+    fetch_user_info(&DB_POOL, project, wallet).await
+}
+
+async fn fetch_user_info(
+    pool: &PgPool,
+    project: &str,
+    wallet: &str,
+) -> Result<UserInfo, sqlx::Error> {
     let user = sqlx::query!(
-        "SELECT id, total_points, has_claimed FROM users WHERE wallet_address = $1",
+        "SELECT id, total_points, has_claimed, display_name FROM users
+         WHERE project_id = $1 AND wallet_address = $2",
+        project,
         wallet
     )
-    .fetch_one(&*DB_POOL)
+    .fetch_one(pool)
     .await?;
 
     let completed_tasks = sqlx::query!(
         "SELECT task_id FROM completed_tasks WHERE user_id = $1",
         user.id
     )
-    .fetch_all(&*DB_POOL)
+    .fetch_all(pool)
     .await?
     .into_iter()
     .map(|r| r.task_id)
@@ -154,23 +584,39 @@ pub async fn get_user_info(wallet: &str) -> Result<UserInfo, sqlx::Error> {
         "SELECT COUNT(*) as count FROM users WHERE referrer_id = $1",
         user.id
     )
-    .fetch_one(&*DB_POOL)
+    .fetch_one(pool)
     .await?
     .count
     .unwrap_or(0);
 
+    // Task points awaiting the next batched sync still count
+    let pending = sqlx::query!(
+        "SELECT COALESCE(SUM(points_awarded), 0)::INT as pending FROM completed_tasks
+         WHERE user_id = $1 AND NOT synced",
+        user.id
+    )
+    .fetch_one(pool)
+    .await?
+    .pending
+    .unwrap_or(0);
+
     Ok(UserInfo {
         wallet: wallet.to_string(),
-        total_points: user.total_points.unwrap_or(0),
+        total_points: user.total_points.unwrap_or(0) + pending,
         tasks_completed: completed_tasks,
         referrals,
         has_claimed: user.has_claimed.unwrap_or(false), // ✅ Add this
+        display_name: user.display_name.unwrap_or_else(|| truncate_wallet(wallet)),
     })
 }
 
-pub async fn get_referral_code_by_wallet(wallet: &str) -> Result<String, sqlx::Error> {
+pub async fn get_referral_code_by_wallet(
+    project: &str,
+    wallet: &str,
+) -> Result<String, sqlx::Error> {
     let res = sqlx::query!(
-        "SELECT referral_code FROM users WHERE wallet_address = $1",
+        "SELECT referral_code FROM users WHERE project_id = $1 AND wallet_address = $2",
+        project,
         wallet
     )
     .fetch_one(&*DB_POOL)
@@ -182,23 +628,370 @@ pub async fn get_referral_code_by_wallet(wallet: &str) -> Result<String, sqlx::E
         .to_string())
 }
 
-// Get all tasks
-pub async fn get_all_tasks() -> Result<Vec<Task>, sqlx::Error> {
-    let records = sqlx::query_as!(Task, "SELECT id, name, points, description FROM tasks")
-        .fetch_all(&*DB_POOL)
+// One page of the project's tasks, optionally only those in `category` and
+// worth at least `min_points`, plus how many tasks match in total
+pub async fn get_tasks_paginated(
+    project: &str,
+    limit: i64,
+    offset: i64,
+    min_points: Option<i32>,
+    category: Option<&str>,
+) -> Result<(Vec<Task>, i64), sqlx::Error> {
+    let tasks = sqlx::query_as!(
+        Task,
+        "SELECT id, name, points, description, category, requires_review, post_claim_allowed FROM tasks
+         WHERE project_id = $1 AND is_active
+           AND ($4::INT IS NULL OR points >= $4)
+           AND ($5::TEXT IS NULL OR category = $5)
+         ORDER BY name ASC, id ASC
+         LIMIT $2 OFFSET $3",
+        project,
+        limit,
+        offset,
+        min_points,
+        category
+    )
+    .fetch_all(&*READ_POOL)
+    .await?;
+
+    let total = sqlx::query!(
+        r#"SELECT COUNT(*) AS "count!" FROM tasks
+           WHERE project_id = $1 AND is_active
+             AND ($2::INT IS NULL OR points >= $2)
+             AND ($3::TEXT IS NULL OR category = $3)"#,
+        project,
+        min_points,
+        category
+    )
+    .fetch_one(&*READ_POOL)
+    .await?
+    .count;
+
+    Ok((tasks, total))
+}
+
+pub async fn create_task(
+    project: &str,
+    name: &str,
+    points: i32,
+    description: Option<&str>,
+    category: Option<&str>,
+) -> Result<Task, sqlx::Error> {
+    sqlx::query_as!(
+        Task,
+        "INSERT INTO tasks (project_id, name, points, description, category)
+         VALUES ($1, $2, $3, $4, COALESCE($5, 'general'))
+         RETURNING id, name, points, description, category, requires_review, post_claim_allowed",
+        project,
+        name,
+        points,
+        description,
+        category
+    )
+    .fetch_one(&*DB_POOL)
+    .await
+}
+
+// Update only the fields that are given; None if the project has no such task
+pub async fn update_task(
+    project: &str,
+    task_id: Uuid,
+    name: Option<&str>,
+    points: Option<i32>,
+    description: Option<&str>,
+    category: Option<&str>,
+) -> Result<Option<Task>, sqlx::Error> {
+    sqlx::query_as!(
+        Task,
+        "UPDATE tasks SET
+             name = COALESCE($1, name),
+             points = COALESCE($2, points),
+             description = COALESCE($3, description),
+             category = COALESCE($6, category)
+         WHERE id = $4 AND project_id = $5
+         RETURNING id, name, points, description, category, requires_review, post_claim_allowed",
+        name,
+        points,
+        description,
+        task_id,
+        project,
+        category
+    )
+    .fetch_optional(&*DB_POOL)
+    .await
+}
+
+// Verification settings for a task; None if the project has no such task
+pub async fn get_task_verification(
+    project: &str,
+    task_id: Uuid,
+) -> Result<Option<TaskVerification>, sqlx::Error> {
+    sqlx::query_as!(
+        TaskVerification,
+        "SELECT is_active, requires_review, verify_type, verify_mint, verify_min_amount
+         FROM tasks WHERE id = $1 AND project_id = $2",
+        task_id,
+        project
+    )
+    .fetch_optional(&*DB_POOL)
+    .await
+}
+
+// Retire a task: it disappears from the task list and can no longer be
+// completed, while existing completions keep pointing at it. False if the
+// project has no such task.
+pub async fn deactivate_task(project: &str, task_id: Uuid) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        "UPDATE tasks SET is_active = FALSE WHERE id = $1 AND project_id = $2",
+        task_id,
+        project
+    )
+    .execute(&*DB_POOL)
+    .await?;
+    Ok(res.rows_affected() == 1)
+}
+
+// Store a proof for a reviewed task instead of crediting it. Same rules as
+// complete_task, plus AlreadySubmitted for a second pending submission for
+// the same task.
+pub async fn submit_task_proof(
+    project: &str,
+    wallet: &str,
+    task_id: Uuid,
+    proof: &str,
+) -> Result<Uuid, TaskError> {
+    let user = sqlx::query!(
+        "SELECT id, has_claimed FROM users WHERE project_id = $1 AND wallet_address = $2",
+        project,
+        wallet
+    )
+    .fetch_optional(&*DB_POOL)
+    .await?
+    .ok_or(TaskError::UserNotFound)?;
+
+    let task = sqlx::query!(
+        "SELECT post_claim_allowed FROM tasks
+         WHERE id = $1 AND project_id = $2 AND is_active",
+        task_id,
+        project
+    )
+    .fetch_optional(&*DB_POOL)
+    .await?
+    .ok_or(TaskError::TaskNotFound)?;
+
+    if task_completed(&user.id, task_id).await? {
+        return Err(TaskError::AlreadyCompleted);
+    }
+
+    if user.has_claimed.unwrap_or(false) && !task.post_claim_allowed {
+        return Err(TaskError::AlreadyClaimed);
+    }
+
+    let row = sqlx::query!(
+        "INSERT INTO task_submissions (user_id, task_id, proof)
+         VALUES ($1, $2, $3)
+         ON CONFLICT (user_id, task_id) WHERE status = 'pending' DO NOTHING
+         RETURNING id",
+        user.id,
+        task_id,
+        proof
+    )
+    .fetch_optional(&*DB_POOL)
+    .await?
+    .ok_or(TaskError::AlreadySubmitted)?;
+
+    Ok(row.id)
+}
+
+// Oldest first, so reviewers work through the queue in order
+pub async fn get_pending_submissions(
+    project: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<TaskSubmission>, sqlx::Error> {
+    sqlx::query_as!(
+        TaskSubmission,
+        r#"SELECT s.id, u.wallet_address as wallet, s.task_id, t.name as task_name,
+                  s.proof, s.status, s.created_at, s.reviewed_at
+         FROM task_submissions s
+         JOIN users u ON u.id = s.user_id
+         JOIN tasks t ON t.id = s.task_id
+         WHERE s.status = 'pending' AND t.project_id = $3
+         ORDER BY s.created_at ASC, s.id ASC
+         LIMIT $1 OFFSET $2"#,
+        limit,
+        offset,
+        project
+    )
+    .fetch_all(&*DB_POOL)
+    .await
+}
+
+// Approve or reject a pending submission for one of the project's tasks; None
+// if there is no such pending submission (another project's included). Approval
+// records the completion and credits the task's points in the same
+// transaction. No points if the user completed the task some other way or
+// has since claimed.
+pub async fn review_submission(
+    project: &str,
+    submission_id: Uuid,
+    approve: bool,
+) -> Result<Option<TaskSubmission>, sqlx::Error> {
+    let mut tx = DB_POOL.begin().await?;
+    let mut ledger = Ledger::default();
+
+    let Some(submission) = sqlx::query!(
+        "SELECT s.user_id, s.task_id FROM task_submissions s
+         JOIN tasks t ON t.id = s.task_id
+         WHERE s.id = $1 AND s.status = 'pending' AND t.project_id = $2
+         FOR UPDATE OF s",
+        submission_id,
+        project
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    if approve {
+        let task = sqlx::query!(
+            "SELECT points, project_id FROM tasks WHERE id = $1",
+            submission.task_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let user = sqlx::query!(
+            "SELECT has_claimed FROM users WHERE id = $1 FOR UPDATE",
+            submission.user_id
+        )
+        .fetch_one(&mut *tx)
         .await?;
-    Ok(records)
+        let claimed = user.has_claimed.unwrap_or(false);
+        let points = if claimed { 0 } else { task.points };
+
+        let inserted = sqlx::query!(
+            "INSERT INTO completed_tasks (user_id, task_id, points_awarded, project_id)
+             VALUES ($1, $2, $3, $4)
+             ON CONFLICT (user_id, task_id) DO NOTHING",
+            submission.user_id,
+            submission.task_id,
+            points,
+            task.project_id
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            > 0;
+
+        if inserted && points > 0 {
+            sqlx::query!(
+                "UPDATE users SET total_points = total_points + $1 WHERE id = $2",
+                points,
+                submission.user_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            ledger
+                .record(
+                    &mut tx,
+                    PointEvent {
+                        user_id: submission.user_id,
+                        delta: points,
+                        reason: "task".to_string(),
+                        reference_id: Some(submission.task_id.to_string()),
+                    },
+                )
+                .await?;
+        }
+    }
+
+    let status = if approve { "approved" } else { "rejected" };
+    let reviewed = sqlx::query_as!(
+        TaskSubmission,
+        r#"WITH s AS (
+             UPDATE task_submissions SET status = $1, reviewed_at = NOW()
+             WHERE id = $2
+               AND task_id IN (SELECT id FROM tasks WHERE project_id = $3)
+             RETURNING *
+         )
+         SELECT s.id, u.wallet_address as wallet, s.task_id, t.name as task_name,
+                s.proof, s.status, s.created_at, s.reviewed_at
+         FROM s
+         JOIN users u ON u.id = s.user_id
+         JOIN tasks t ON t.id = s.task_id"#,
+        status,
+        submission_id,
+        project
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    ledger.commit();
+    Ok(Some(reviewed))
+}
+
+// Up to `limit` of the project's wallets, for exercising read paths
+pub async fn sample_wallets(project: &str, limit: i64) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query!(
+        "SELECT wallet_address FROM users WHERE project_id = $1 LIMIT $2",
+        project,
+        limit
+    )
+    .fetch_all(&*READ_POOL)
+    .await?;
+    Ok(rows.into_iter().map(|r| r.wallet_address).collect())
 }
 
 pub async fn get_wallet_count() -> Result<i64, sqlx::Error> {
     let row = sqlx::query!("SELECT COUNT(*) as count FROM users")
-        .fetch_one(&*DB_POOL)
+        .fetch_one(&*READ_POOL)
         .await?;
 
     Ok(row.count.unwrap_or(0))
 }
 
-pub async fn log_airdrop(wallet: &str, amount: i32, sig: &str) -> Result<(), sqlx::Error> {
+// Wallet count for the stats endpoint without a COUNT(*) per hit. Bumped on
+// each new user and re-read from the table every WALLET_COUNT_REFRESH_MS, so
+// it is approximately right between refreshes. -1 until first loaded.
+static WALLET_COUNT: AtomicI64 = AtomicI64::new(-1);
+
+pub async fn get_wallet_count_cached() -> Result<i64, sqlx::Error> {
+    match WALLET_COUNT.load(Ordering::Relaxed) {
+        -1 => refresh_wallet_count().await,
+        count => Ok(count),
+    }
+}
+
+async fn refresh_wallet_count() -> Result<i64, sqlx::Error> {
+    let count = get_wallet_count().await?;
+    WALLET_COUNT.store(count, Ordering::Relaxed);
+    Ok(count)
+}
+
+pub fn start_wallet_count_refresh() {
+    let interval = std::time::Duration::from_millis(CONFIG.wallet_count_refresh_ms);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = refresh_wallet_count().await {
+                tracing::warn!(error = %e, "Failed to refresh cached wallet count");
+            }
+        }
+    });
+}
+
+// `amount` is in UI tokens (e.g. 1000), not base units
+pub async fn log_airdrop(
+    conn: &mut PgConnection,
+    wallet: &str,
+    amount: i64,
+    sig: &str,
+) -> Result<(), sqlx::Error> {
     sqlx::query!(
         "INSERT INTO airdrop_log (wallet_address, amount_sent, tx_signature)
          VALUES ($1, $2, $3)",
@@ -206,30 +999,320 @@ pub async fn log_airdrop(wallet: &str, amount: i32, sig: &str) -> Result<(), sql
         amount,
         sig
     )
-    .execute(&*DB_POOL)
+    .execute(conn)
     .await?;
     Ok(())
 }
 
-pub async fn set_claimed(wallet: &str) -> Result<(), sqlx::Error> {
+// Attach on-chain confirmation details to the airdrop sent in `sig`
+pub async fn set_airdrop_confirmation(
+    sig: &str,
+    confirmation: &TxConfirmation,
+) -> Result<(), sqlx::Error> {
+    let block_time = confirmation
+        .block_time
+        .and_then(|t| chrono::DateTime::from_timestamp(t, 0));
     sqlx::query!(
-        "UPDATE users SET has_claimed = TRUE WHERE wallet_address = $1",
-        wallet
+        "UPDATE airdrop_log
+         SET slot = $1, block_time = $2, fee_lamports = $3, confirmation_status = $4
+         WHERE tx_signature = $5",
+        confirmation.slot as i64,
+        block_time,
+        confirmation.fee_lamports.map(|f| f as i64),
+        confirmation.status,
+        sig
     )
     .execute(&*DB_POOL)
     .await?;
     Ok(())
 }
 
-pub async fn get_total_airdrops() -> Result<i64, sqlx::Error> {
-    let res = sqlx::query!("SELECT COUNT(*) as count FROM airdrop_log")
-        .fetch_one(&*DB_POOL)
-        .await?;
-    Ok(res.count.unwrap_or(0))
+pub async fn set_claimed(
+    conn: &mut PgConnection,
+    project: &str,
+    wallet: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE users SET has_claimed = TRUE WHERE project_id = $1 AND wallet_address = $2",
+        project,
+        wallet
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
 }
 
-// pub async fn reset_user_points(wallet: &str) -> Result<(), sqlx::Error> {
-//     sqlx::query!(
+// Take a claim's points and hold its fee, in one transaction, before its
+// transfer is sent. Returns the points left. Refused with
+// ClaimError::InsufficientPoints if the wallet doesn't have `points` (nothing
+// is held then), or ClaimError::FeeAlreadyUsed if another claim holds or
+// burned the fee; of several claims presenting the same fee at once only one
+// gets it. The hold is either given back by release_claim when the transfer
+// fails or settled by finalize_claim once it lands.
+pub async fn reserve_claim(
+    project: &str,
+    wallet: &str,
+    fee_tx: Option<&str>,
+    points: i32,
+) -> Result<i32, ClaimError> {
+    let mut tx = DB_POOL.begin().await?;
+
+    if let Some(fee_tx) = fee_tx {
+        let held = sqlx::query!(
+            "UPDATE fee_payments SET claiming = TRUE
+             WHERE wallet_address = $1 AND tx_signature = $2
+               AND NOT COALESCE(used, FALSE) AND NOT claiming",
+            wallet,
+            fee_tx
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if held == 0 {
+            return Err(ClaimError::FeeAlreadyUsed);
+        }
+    }
+    let remaining = deduct_user_points(&mut tx, project, wallet, points)
+        .await?
+        .ok_or(ClaimError::InsufficientPoints)?;
+
+    tx.commit().await?;
+    Ok(remaining)
+}
+
+// Undo reserve_claim for a transfer that never went out: the points go back
+// and the fee can be used again
+pub async fn release_claim(
+    project: &str,
+    wallet: &str,
+    fee_tx: Option<&str>,
+    points: i32,
+) -> Result<(), sqlx::Error> {
+    let mut tx = DB_POOL.begin().await?;
+    unreserve_claim(&mut tx, project, wallet, fee_tx, points).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn unreserve_claim(
+    conn: &mut PgConnection,
+    project: &str,
+    wallet: &str,
+    fee_tx: Option<&str>,
+    points: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE users SET total_points = COALESCE(total_points, 0) + $1
+         WHERE project_id = $2 AND wallet_address = $3",
+        points,
+        project,
+        wallet
+    )
+    .execute(&mut *conn)
+    .await?;
+    if let Some(fee_tx) = fee_tx {
+        sqlx::query!(
+            "UPDATE fee_payments SET claiming = FALSE
+             WHERE wallet_address = $1 AND tx_signature = $2 AND NOT COALESCE(used, FALSE)",
+            wallet,
+            fee_tx
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+// Record a successful transfer whose points and fee reserve_claim already
+// holds: log it, enter the deduction in the ledger, flag the user as claimed
+// and burn the fee, all in one transaction.
+//
+// Ordering: reserve_claim runs before the transfer is sent, and the transfer
+// is sent and confirmed (send_tokens) before this is called with its
+// signature. So
+// - a claim the points don't cover, or whose fee another claim has, is
+//   refused before any tokens move;
+// - if the transfer fails, nothing here runs: release_claim gives the points
+//   and the fee back, and the user can simply retry;
+// - the fee is only burned if no other claim has used it; otherwise nothing
+//   is recorded and ClaimError::FeeAlreadyUsed comes back;
+// - the writes commit together or not at all, so a crash or DB error
+//   never leaves a claim half recorded (e.g. logged but the fee still
+//   reusable);
+// - the one gap is a failure after the transfer confirmed but before this
+//   commits. The tokens are then out but the claim isn't recorded; the points
+//   and fee stay held, and the caller logs the signature so it can be
+//   reconciled from the chain.
+// A transfer that was still unconfirmed when the claim stopped waiting is
+// parked in pending_airdrops instead, and the reconciler calls this once it
+// lands; its pending row is removed in the same transaction.
+// Ledger entries are only queued once the transaction has committed.
+pub async fn finalize_claim(
+    project: &str,
+    wallet: &str,
+    amount: i64,
+    sig: &str,
+    fee_tx: Option<&str>,
+    points: i32,
+) -> Result<(), ClaimError> {
+    let mut tx = DB_POOL.begin().await?;
+    let mut ledger = Ledger::default();
+
+    log_airdrop(&mut tx, wallet, amount, sig).await?;
+    if points > 0 {
+        let user = sqlx::query!(
+            "SELECT id FROM users WHERE project_id = $1 AND wallet_address = $2",
+            project,
+            wallet
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        ledger
+            .record(
+                &mut tx,
+                PointEvent {
+                    user_id: user.id,
+                    delta: -points,
+                    reason: "claim".to_string(),
+                    reference_id: Some(sig.to_string()),
+                },
+            )
+            .await?;
+    }
+    set_claimed(&mut tx, project, wallet).await?;
+    if let Some(fee_tx) = fee_tx {
+        if !mark_fee_used(&mut tx, wallet, fee_tx).await? {
+            return Err(ClaimError::FeeAlreadyUsed);
+        }
+    }
+    sqlx::query!("DELETE FROM pending_airdrops WHERE signature = $1", sig)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    ledger.commit();
+    Ok(())
+}
+
+pub async fn insert_pending_airdrop(pending: &PendingAirdrop) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO pending_airdrops
+           (signature, project_id, wallet_address, mint, amount, points, fee_tx, last_valid_block_height)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        pending.signature,
+        pending.project_id,
+        pending.wallet_address,
+        pending.mint,
+        pending.amount,
+        pending.points,
+        pending.fee_tx,
+        pending.last_valid_block_height
+    )
+    .execute(&*DB_POOL)
+    .await?;
+    Ok(())
+}
+
+pub async fn has_pending_airdrop(project: &str, wallet: &str) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"SELECT EXISTS(
+             SELECT 1 FROM pending_airdrops WHERE project_id = $1 AND wallet_address = $2
+         ) as "pending!""#,
+        project,
+        wallet
+    )
+    .fetch_one(&*DB_POOL)
+    .await?;
+    Ok(row.pending)
+}
+
+// Oldest unreconciled transfers first
+pub async fn get_pending_airdrops(limit: i64) -> Result<Vec<PendingAirdrop>, sqlx::Error> {
+    sqlx::query_as!(
+        PendingAirdrop,
+        "SELECT signature, project_id, wallet_address, mint, amount, points, fee_tx,
+                last_valid_block_height
+         FROM pending_airdrops
+         ORDER BY created_at
+         LIMIT $1",
+        limit
+    )
+    .fetch_all(&*DB_POOL)
+    .await
+}
+
+// For a transfer that failed or expired without landing; its points and fee
+// are given back so the wallet can claim again
+pub async fn drop_pending_airdrop(pending: &PendingAirdrop) -> Result<(), sqlx::Error> {
+    let mut tx = DB_POOL.begin().await?;
+    sqlx::query!(
+        "DELETE FROM pending_airdrops WHERE signature = $1",
+        pending.signature
+    )
+    .execute(&mut *tx)
+    .await?;
+    unreserve_claim(
+        &mut tx,
+        &pending.project_id,
+        &pending.wallet_address,
+        pending.fee_tx.as_deref(),
+        pending.points,
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+// Totals scan every user and ledger row, so they are cached this long
+const POINTS_TOTALS_TTL: std::time::Duration = std::time::Duration::from_secs(60);
+static POINTS_TOTALS: Lazy<Mutex<Option<(PointsTotals, std::time::Instant)>>> =
+    Lazy::new(|| Mutex::new(None));
+
+// System-wide points: what users hold now, what has been awarded and what
+// has been deducted, per the ledger. Claims made before the ledger recorded
+// them aren't counted as deducted.
+pub async fn get_points_totals() -> Result<PointsTotals, sqlx::Error> {
+    if let Some((totals, at)) = *POINTS_TOTALS.lock().unwrap() {
+        if at.elapsed() < POINTS_TOTALS_TTL {
+            return Ok(totals);
+        }
+    }
+
+    let row = sqlx::query!(
+        r#"SELECT
+             (SELECT COALESCE(SUM(total_points), 0) FROM users)::BIGINT AS "circulating!",
+             (SELECT COALESCE(SUM(delta), 0) FROM point_events WHERE delta > 0)::BIGINT
+               AS "awarded!",
+             (SELECT COALESCE(-SUM(delta), 0) FROM point_events WHERE delta < 0)::BIGINT
+               AS "deducted!""#
+    )
+    .fetch_one(&*READ_POOL)
+    .await?;
+
+    let totals = PointsTotals {
+        circulating: row.circulating,
+        awarded: row.awarded,
+        deducted: row.deducted,
+    };
+    *POINTS_TOTALS.lock().unwrap() = Some((totals, std::time::Instant::now()));
+    Ok(totals)
+}
+
+// Cheapest possible round trip to the primary, for readiness checks
+pub async fn ping() -> Result<(), sqlx::Error> {
+    sqlx::query!("SELECT 1 AS one").fetch_one(&*DB_POOL).await?;
+    Ok(())
+}
+
+pub async fn get_total_airdrops() -> Result<i64, sqlx::Error> {
+    let res = sqlx::query!("SELECT COUNT(*) as count FROM airdrop_log")
+        .fetch_one(&*READ_POOL)
+        .await?;
+    Ok(res.count.unwrap_or(0))
+}
+
+// pub async fn reset_user_points(wallet: &str) -> Result<(), sqlx::Error> {
+//     sqlx::query!(
 //         "UPDATE users SET total_points = 0 WHERE wallet_address = $1",
 //         wallet
 //     )
@@ -249,25 +1332,43 @@ pub async fn get_total_airdrops() -> Result<i64, sqlx::Error> {
 //     Ok(())
 // }
 
-pub async fn deduct_user_points(wallet: &str, amount: i32) -> Result<(), sqlx::Error> {
-    sqlx::query!(
-        "UPDATE users SET total_points = total_points - $1 WHERE wallet_address = $2",
-        amount,
+// Deduct points and return the new balance, or None (changing nothing) if
+// the user has fewer than `amount`. The ledger entry is the caller's, since a
+// claim's points are taken before its transfer has a signature.
+pub async fn deduct_user_points(
+    conn: &mut PgConnection,
+    project: &str,
+    wallet: &str,
+    amount: i32,
+) -> Result<Option<i32>, sqlx::Error> {
+    let user = sqlx::query!(
+        "SELECT id FROM users WHERE project_id = $1 AND wallet_address = $2 FOR UPDATE",
+        project,
         wallet
     )
-    .execute(&*DB_POOL)
+    .fetch_one(&mut *conn)
     .await?;
-    Ok(())
+
+    // Fold in batched task points first so they aren't lost to the floor
+    sync_pending_points(&mut *conn, &user.id).await?;
+
+    let row = sqlx::query!(
+        r#"UPDATE users SET total_points = COALESCE(total_points, 0) - $1
+         WHERE id = $2 AND COALESCE(total_points, 0) >= $1
+         RETURNING total_points as "total_points!""#,
+        amount,
+        user.id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    Ok(row.map(|r| r.total_points))
 }
 
 pub async fn record_fee_if_new(wallet: &str, tx: &str) -> Result<bool, sqlx::Error> {
     // Check if the transaction has already been recorded
-    let exists = sqlx::query!(
-        "SELECT used FROM fee_payments WHERE tx_signature = $1",
-        tx
-    )
-    .fetch_optional(&*DB_POOL)
-    .await?;
+    let exists = sqlx::query!("SELECT used FROM fee_payments WHERE tx_signature = $1", tx)
+        .fetch_optional(&*DB_POOL)
+        .await?;
 
     if let Some(record) = exists {
         // Already recorded
@@ -289,13 +1390,840 @@ pub async fn record_fee_if_new(wallet: &str, tx: &str) -> Result<bool, sqlx::Err
     }
 }
 
-pub async fn mark_fee_used(wallet: &str, tx: &str) -> Result<(), sqlx::Error> {
-    sqlx::query!(
-        "UPDATE fee_payments SET used = TRUE WHERE wallet_address = $1 AND tx_signature = $2",
+// Burn a fee for the claim it paid for. False if it was already used, in which
+// case the caller must not record the claim.
+pub async fn mark_fee_used(
+    conn: &mut PgConnection,
+    wallet: &str,
+    tx: &str,
+) -> Result<bool, sqlx::Error> {
+    let marked = sqlx::query!(
+        "UPDATE fee_payments SET used = TRUE, claiming = FALSE
+         WHERE wallet_address = $1 AND tx_signature = $2 AND NOT COALESCE(used, FALSE)",
         wallet,
         tx
     )
+    .execute(conn)
+    .await?
+    .rows_affected();
+    Ok(marked == 1)
+}
+
+// Issue a new sign-in nonce for a wallet
+pub async fn create_auth_nonce(wallet: &str) -> Result<AuthNonce, sqlx::Error> {
+    sqlx::query_as!(
+        AuthNonce,
+        "INSERT INTO auth_nonces (wallet_address) VALUES ($1)
+         RETURNING nonce, wallet_address, used, created_at",
+        wallet
+    )
+    .fetch_one(&*DB_POOL)
+    .await
+}
+
+pub async fn get_auth_nonce(nonce: &Uuid) -> Result<Option<AuthNonce>, sqlx::Error> {
+    sqlx::query_as!(
+        AuthNonce,
+        "SELECT nonce, wallet_address, used, created_at FROM auth_nonces WHERE nonce = $1",
+        nonce
+    )
+    .fetch_optional(&*DB_POOL)
+    .await
+}
+
+// Mark a nonce used; false if it was already used
+pub async fn consume_auth_nonce(nonce: &Uuid) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        "UPDATE auth_nonces SET used = TRUE WHERE nonce = $1 AND used = FALSE",
+        nonce
+    )
+    .execute(&*DB_POOL)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+// Set the user's display name; Ok(false) if the wallet isn't registered
+pub async fn set_display_name(
+    project: &str,
+    wallet: &str,
+    name: &str,
+) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        "UPDATE users SET display_name = $1 WHERE project_id = $2 AND wallet_address = $3",
+        name,
+        project,
+        wallet
+    )
+    .execute(&*DB_POOL)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+pub async fn set_contact(
+    project: &str,
+    wallet: &str,
+    email: Option<&str>,
+    webhook_url: Option<&str>,
+    notify_on_claim: bool,
+) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        "UPDATE users SET contact_email = $1, contact_webhook_url = $2, notify_on_claim = $3
+         WHERE project_id = $4 AND wallet_address = $5",
+        email,
+        webhook_url,
+        notify_on_claim,
+        project,
+        wallet
+    )
+    .execute(&*DB_POOL)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+// Contact details for a receipt, only if the user opted in
+pub async fn get_claim_contact(
+    project: &str,
+    wallet: &str,
+) -> Result<Option<ClaimContact>, sqlx::Error> {
+    sqlx::query_as!(
+        ClaimContact,
+        "SELECT contact_email, contact_webhook_url FROM users
+         WHERE project_id = $1 AND wallet_address = $2 AND notify_on_claim",
+        project,
+        wallet
+    )
+    .fetch_optional(&*DB_POOL)
+    .await
+}
+
+// Partner fee override in lamports, if one is set for the wallet
+pub async fn get_fee_override(project: &str, wallet: &str) -> Result<Option<i64>, sqlx::Error> {
+    let res = sqlx::query!(
+        "SELECT fee_override_lamports FROM users WHERE project_id = $1 AND wallet_address = $2",
+        project,
+        wallet
+    )
+    .fetch_optional(&*DB_POOL)
+    .await?;
+    Ok(res.and_then(|r| r.fee_override_lamports))
+}
+
+pub async fn set_fee_override(
+    project: &str,
+    wallet: &str,
+    lamports: Option<i64>,
+) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        "UPDATE users SET fee_override_lamports = $1 WHERE project_id = $2 AND wallet_address = $3",
+        lamports,
+        project,
+        wallet
+    )
+    .execute(&*DB_POOL)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+// Ask the ATA worker to create this wallet's token account for the project's
+// mint; no-op if it is already queued
+pub async fn queue_ata_creation(project: &str, wallet: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO pending_ata_creation (project_id, wallet_address) VALUES ($1, $2)
+         ON CONFLICT DO NOTHING",
+        project,
+        wallet
+    )
     .execute(&*DB_POOL)
     .await?;
     Ok(())
 }
+
+// Oldest queued wallets that haven't used up their attempts
+pub async fn get_pending_ata_creations(
+    max_attempts: i32,
+    limit: i64,
+) -> Result<Vec<PendingAtaCreation>, sqlx::Error> {
+    sqlx::query_as!(
+        PendingAtaCreation,
+        "SELECT project_id, wallet_address FROM pending_ata_creation
+         WHERE attempts < $1
+         ORDER BY queued_at
+         LIMIT $2",
+        max_attempts,
+        limit
+    )
+    .fetch_all(&*DB_POOL)
+    .await
+}
+
+pub async fn complete_ata_creation(project: &str, wallet: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM pending_ata_creation WHERE project_id = $1 AND wallet_address = $2",
+        project,
+        wallet
+    )
+    .execute(&*DB_POOL)
+    .await?;
+    Ok(())
+}
+
+pub async fn fail_ata_creation(
+    project: &str,
+    wallet: &str,
+    error: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE pending_ata_creation SET attempts = attempts + 1, last_error = $1
+         WHERE project_id = $2 AND wallet_address = $3",
+        error,
+        project,
+        wallet
+    )
+    .execute(&*DB_POOL)
+    .await?;
+    Ok(())
+}
+
+pub async fn is_beta_wallet(wallet: &str) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"SELECT EXISTS(
+             SELECT 1 FROM beta_allowlist WHERE wallet_address = $1
+         ) as "allowed!""#,
+        wallet
+    )
+    .fetch_one(&*DB_POOL)
+    .await?;
+    Ok(row.allowed)
+}
+
+// Returns false if the wallet was already listed
+pub async fn add_beta_wallet(wallet: &str) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        "INSERT INTO beta_allowlist (wallet_address) VALUES ($1) ON CONFLICT DO NOTHING",
+        wallet
+    )
+    .execute(&*DB_POOL)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+// Returns false if the wallet wasn't listed
+pub async fn remove_beta_wallet(wallet: &str) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        "DELETE FROM beta_allowlist WHERE wallet_address = $1",
+        wallet
+    )
+    .execute(&*DB_POOL)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+pub async fn get_project(id: &str) -> Result<Option<Project>, sqlx::Error> {
+    sqlx::query_as!(
+        Project,
+        "SELECT id, token_mint, required_fee_lamports, min_claim_points, airdrop_tokens
+         FROM projects WHERE id = $1",
+        id
+    )
+    .fetch_optional(&*READ_POOL)
+    .await
+}
+
+// The campaign the project is currently distributing, if any
+pub async fn get_active_campaign(project: &str) -> Result<Option<Campaign>, sqlx::Error> {
+    sqlx::query_as!(
+        Campaign,
+        "SELECT id, name, token_mint, airdrop_amount, is_active
+         FROM campaigns WHERE project_id = $1 AND is_active",
+        project
+    )
+    .fetch_optional(&*DB_POOL)
+    .await
+}
+
+// Append an entry to the points ledger; run it on the same transaction as
+// the balance change it describes
+pub async fn record_point_event(
+    conn: &mut PgConnection,
+    user_id: &Uuid,
+    delta: i32,
+    reason: &str,
+    reference_id: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO point_events (user_id, delta, reason, reference_id)
+         VALUES ($1, $2, $3, $4)",
+        user_id,
+        delta,
+        reason,
+        reference_id
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+// Write a batch of buffered ledger entries in one statement
+pub async fn insert_point_events(events: &[PointEvent]) -> Result<(), sqlx::Error> {
+    let user_ids: Vec<Uuid> = events.iter().map(|e| e.user_id).collect();
+    let deltas: Vec<i32> = events.iter().map(|e| e.delta).collect();
+    let reasons: Vec<String> = events.iter().map(|e| e.reason.clone()).collect();
+    let reference_ids: Vec<Option<String>> =
+        events.iter().map(|e| e.reference_id.clone()).collect();
+
+    sqlx::query!(
+        "INSERT INTO point_events (user_id, delta, reason, reference_id)
+         SELECT * FROM UNNEST($1::UUID[], $2::INT[], $3::TEXT[], $4::TEXT[])",
+        &user_ids,
+        &deltas,
+        &reasons,
+        &reference_ids as &[Option<String>]
+    )
+    .execute(&*DB_POOL)
+    .await?;
+    Ok(())
+}
+
+// Move a user's unsynced task points into total_points and return the
+// resulting total. Callers hold the users row lock.
+async fn sync_pending_points(conn: &mut PgConnection, user_id: &Uuid) -> Result<i32, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"WITH pending AS (
+             UPDATE completed_tasks SET synced = TRUE
+             WHERE user_id = $1 AND NOT synced
+             RETURNING points_awarded
+         )
+         UPDATE users
+         SET total_points = COALESCE(total_points, 0)
+             + (SELECT COALESCE(SUM(points_awarded), 0)::INT FROM pending)
+         WHERE id = $1
+         RETURNING total_points as "total_points!""#,
+        user_id
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(row.total_points)
+}
+
+// Apply one user's batched task points now; returns their synced total
+pub async fn resync_points(user_id: &Uuid) -> Result<i32, sqlx::Error> {
+    let mut tx = DB_POOL.begin().await?;
+    sqlx::query!("SELECT id FROM users WHERE id = $1 FOR UPDATE", user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    let total = sync_pending_points(&mut tx, user_id).await?;
+    tx.commit().await?;
+    Ok(total)
+}
+
+// Apply every user's batched task points in one statement; returns how many
+// users were updated
+pub async fn sync_all_pending_points() -> Result<u64, sqlx::Error> {
+    let res = sqlx::query!(
+        "WITH pending AS (
+             UPDATE completed_tasks SET synced = TRUE
+             WHERE NOT synced
+             RETURNING user_id, points_awarded
+         )
+         UPDATE users u
+         SET total_points = COALESCE(u.total_points, 0) + p.points
+         FROM (
+             SELECT user_id, SUM(points_awarded)::INT AS points
+             FROM pending
+             GROUP BY user_id
+         ) p
+         WHERE u.id = p.user_id"
+    )
+    .execute(&*DB_POOL)
+    .await?;
+    Ok(res.rows_affected())
+}
+
+// Background job folding batched task points into users.total_points
+pub fn start_points_sync() {
+    if !CONFIG.points_batch_sync {
+        return;
+    }
+    let interval = std::time::Duration::from_millis(CONFIG.points_sync_interval_ms);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match sync_all_pending_points().await {
+                Ok(0) => {}
+                Ok(users) => tracing::debug!(users, "Synced batched task points"),
+                Err(e) => tracing::warn!(error = %e, "Batched points sync failed"),
+            }
+        }
+    });
+}
+
+// Manually add/remove points, clamped to [0, cap]. Returns (old, new) totals,
+// or None if the wallet isn't registered.
+pub async fn adjust_user_points(
+    project: &str,
+    wallet: &str,
+    delta: i32,
+    reason: &str,
+    cap: Option<i32>,
+) -> Result<Option<(i32, i32)>, sqlx::Error> {
+    let mut tx = DB_POOL.begin().await?;
+
+    let Some(user) = sqlx::query!(
+        "SELECT id FROM users WHERE project_id = $1 AND wallet_address = $2 FOR UPDATE",
+        project,
+        wallet
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let old = sync_pending_points(&mut tx, &user.id).await?;
+    let mut new = (old as i64 + delta as i64).max(0);
+    if let Some(cap) = cap {
+        new = new.min(cap as i64);
+    }
+    let new = new as i32;
+
+    sqlx::query!(
+        "UPDATE users SET total_points = $1 WHERE id = $2",
+        new,
+        user.id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let mut ledger = Ledger::default();
+    ledger
+        .record(
+            &mut tx,
+            PointEvent {
+                user_id: user.id,
+                delta: new - old,
+                reason: format!("admin: {}", reason),
+                reference_id: None,
+            },
+        )
+        .await?;
+
+    tx.commit().await?;
+    ledger.commit();
+    Ok(Some((old, new)))
+}
+
+// Top referrers in the project by number of referred users
+pub async fn get_referral_leaderboard(
+    project: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<ReferralLeaderboardEntry>, sqlx::Error> {
+    let rows = sqlx::query!(
+        "SELECT u.wallet_address, u.display_name, r.referrals
+         FROM (
+             SELECT referrer_id, COUNT(*) AS referrals
+             FROM users
+             WHERE referrer_id IS NOT NULL
+             GROUP BY referrer_id
+         ) r
+         JOIN users u ON u.id = r.referrer_id
+         WHERE u.project_id = $3
+         ORDER BY r.referrals DESC, u.id ASC
+         LIMIT $1 OFFSET $2",
+        limit,
+        offset,
+        project
+    )
+    .fetch_all(&*READ_POOL)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .enumerate()
+        .map(|(i, r)| ReferralLeaderboardEntry {
+            rank: offset + i as i64 + 1,
+            display_name: r
+                .display_name
+                .unwrap_or_else(|| truncate_wallet(&r.wallet_address)),
+            wallet: public_wallet(&r.wallet_address),
+            referrals: r.referrals.unwrap_or(0),
+        })
+        .collect())
+}
+
+// Users by total_points, ties broken by signup order so pages are stable
+pub async fn get_leaderboard(
+    project: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<PointsLeaderboardEntry>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"SELECT wallet_address, display_name, COALESCE(total_points, 0) AS "total_points!"
+           FROM users
+           WHERE project_id = $3
+           ORDER BY COALESCE(total_points, 0) DESC, id ASC
+           LIMIT $1 OFFSET $2"#,
+        limit,
+        offset,
+        project
+    )
+    .fetch_all(&*READ_POOL)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .enumerate()
+        .map(|(i, r)| PointsLeaderboardEntry {
+            rank: offset + i as i64 + 1,
+            display_name: r
+                .display_name
+                .unwrap_or_else(|| truncate_wallet(&r.wallet_address)),
+            wallet: public_wallet(&r.wallet_address),
+            total_points: r.total_points,
+        })
+        .collect())
+}
+
+// A wallet's (rank, total_points) in the same order as get_leaderboard, or
+// None if it isn't registered
+pub async fn get_user_rank(project: &str, wallet: &str) -> Result<Option<(i64, i32)>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"WITH me AS (
+               SELECT id, project_id, COALESCE(total_points, 0) AS points
+               FROM users WHERE project_id = $1 AND wallet_address = $2
+           )
+           SELECT me.points AS "total_points!",
+                  (SELECT COUNT(*) FROM users o
+                   WHERE o.project_id = me.project_id
+                     AND (COALESCE(o.total_points, 0) > me.points
+                          OR (COALESCE(o.total_points, 0) = me.points AND o.id < me.id))) + 1
+                  AS "rank!"
+           FROM me"#,
+        project,
+        wallet
+    )
+    .fetch_optional(&*READ_POOL)
+    .await?;
+    Ok(row.map(|r| (r.rank, r.total_points)))
+}
+
+// A wallet's point changes, newest first. The sum of all entries is the
+// wallet's balance (once batched task points have synced).
+pub async fn get_points_history(
+    project: &str,
+    wallet: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<PointHistoryEntry>, sqlx::Error> {
+    sqlx::query_as!(
+        PointHistoryEntry,
+        "SELECT e.delta, e.reason, e.reference_id, e.created_at
+         FROM point_events e
+         JOIN users u ON u.id = e.user_id
+         WHERE u.project_id = $1 AND u.wallet_address = $2
+         ORDER BY e.created_at DESC
+         LIMIT $3 OFFSET $4",
+        project,
+        wallet,
+        limit,
+        offset
+    )
+    .fetch_all(&*READ_POOL)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        block_on, random_project, random_signature, random_wallet, user_with_points,
+    };
+
+    async fn points_of(wallet: &str) -> i32 {
+        points_in("default", wallet).await
+    }
+
+    async fn points_in(project: &str, wallet: &str) -> i32 {
+        get_user_info_fresh(project, wallet)
+            .await
+            .unwrap()
+            .total_points
+    }
+
+    // Users linked top-down: each one is referred by the one before it
+    async fn referral_chain(len: usize) -> Vec<Uuid> {
+        let mut ids: Vec<Uuid> = Vec::new();
+        for _ in 0..len {
+            let id = create_user("default", &random_wallet()).await.unwrap();
+            if let Some(referrer) = ids.last() {
+                sqlx::query!(
+                    "UPDATE users SET referrer_id = $1 WHERE id = $2",
+                    referrer,
+                    id
+                )
+                .execute(&*DB_POOL)
+                .await
+                .unwrap();
+            }
+            ids.push(id);
+        }
+        ids
+    }
+
+    #[test]
+    fn concurrent_claims_cannot_share_a_fee() {
+        block_on(async {
+            let (wallet, fee) = (user_with_points(10_000).await, random_signature());
+            assert!(record_fee_if_new(&wallet, &fee).await.unwrap());
+
+            let claims: Vec<_> = (0..4)
+                .map(|_| {
+                    let (wallet, fee) = (wallet.clone(), fee.clone());
+                    tokio::spawn(async move {
+                        reserve_claim("default", &wallet, Some(&fee), 1_000).await
+                    })
+                })
+                .collect();
+            let mut held = 0;
+            for claim in claims {
+                match claim.await.unwrap() {
+                    Ok(_) => held += 1,
+                    Err(ClaimError::FeeAlreadyUsed) => {}
+                    Err(e) => panic!("unexpected {}", e),
+                }
+            }
+            assert_eq!(held, 1);
+            assert_eq!(points_of(&wallet).await, 9_000);
+        });
+    }
+
+    #[test]
+    fn failed_transfer_releases_the_points_and_fee_for_a_retry() {
+        block_on(async {
+            let (wallet, fee) = (user_with_points(1_000).await, random_signature());
+            record_fee_if_new(&wallet, &fee).await.unwrap();
+            assert_eq!(
+                reserve_claim("default", &wallet, Some(&fee), 1_000)
+                    .await
+                    .unwrap(),
+                0
+            );
+
+            release_claim("default", &wallet, Some(&fee), 1_000)
+                .await
+                .unwrap();
+            assert_eq!(points_of(&wallet).await, 1_000);
+            assert!(record_fee_if_new(&wallet, &fee).await.unwrap());
+            assert!(reserve_claim("default", &wallet, Some(&fee), 1_000)
+                .await
+                .is_ok());
+        });
+    }
+
+    #[test]
+    fn a_fee_is_only_burned_once() {
+        block_on(async {
+            let (wallet, fee) = (user_with_points(2_000).await, random_signature());
+            record_fee_if_new(&wallet, &fee).await.unwrap();
+            reserve_claim("default", &wallet, Some(&fee), 1_000)
+                .await
+                .unwrap();
+
+            let mut conn = DB_POOL.acquire().await.unwrap();
+            assert!(mark_fee_used(&mut conn, &wallet, &fee).await.unwrap());
+            assert!(!mark_fee_used(&mut conn, &wallet, &fee).await.unwrap());
+            assert!(!record_fee_if_new(&wallet, &fee).await.unwrap());
+            assert!(matches!(
+                reserve_claim("default", &wallet, Some(&fee), 1_000).await,
+                Err(ClaimError::FeeAlreadyUsed)
+            ));
+        });
+    }
+
+    #[test]
+    fn over_deducting_leaves_the_balance_unchanged() {
+        block_on(async {
+            let wallet = user_with_points(999).await;
+
+            let mut conn = DB_POOL.acquire().await.unwrap();
+            assert_eq!(
+                deduct_user_points(&mut conn, "default", &wallet, 1_000)
+                    .await
+                    .unwrap(),
+                None
+            );
+            assert_eq!(points_of(&wallet).await, 999);
+        });
+    }
+
+    #[test]
+    fn a_claim_the_points_do_not_cover_holds_nothing() {
+        block_on(async {
+            let (wallet, fee) = (user_with_points(999).await, random_signature());
+            record_fee_if_new(&wallet, &fee).await.unwrap();
+
+            assert!(matches!(
+                reserve_claim("default", &wallet, Some(&fee), 1_000).await,
+                Err(ClaimError::InsufficientPoints)
+            ));
+            assert_eq!(points_of(&wallet).await, 999);
+            // The fee hold was rolled back with the deduction
+            assert!(reserve_claim("default", &wallet, Some(&fee), 999)
+                .await
+                .is_ok());
+        });
+    }
+
+    #[test]
+    fn another_projects_submission_cannot_be_reviewed() {
+        block_on(async {
+            let (own, other) = (random_project().await, random_project().await);
+            let wallet = random_wallet();
+            create_user(&own, &wallet).await.unwrap();
+            let task = create_task(&own, "Reviewed", 10, None, None).await.unwrap();
+            let submission = submit_task_proof(&own, &wallet, task.id, "proof")
+                .await
+                .unwrap();
+
+            assert!(review_submission(&other, submission, true)
+                .await
+                .unwrap()
+                .is_none());
+            assert_eq!(points_in(&own, &wallet).await, 0);
+            assert!(review_submission(&own, submission, true)
+                .await
+                .unwrap()
+                .is_some());
+            assert_eq!(points_in(&own, &wallet).await, 10);
+        });
+    }
+
+    #[test]
+    fn ancestors_are_found_directly_and_through_the_chain() {
+        block_on(async {
+            let chain = referral_chain(3).await;
+            let (a, b, c) = (chain[0], chain[1], chain[2]);
+            let mut conn = DB_POOL.acquire().await.unwrap();
+
+            assert!(is_ancestor(&mut conn, &a, &b, 64).await.unwrap());
+            assert!(is_ancestor(&mut conn, &a, &c, 64).await.unwrap());
+            assert!(!is_ancestor(&mut conn, &c, &a, 64).await.unwrap());
+            // The walk stops after max_depth levels
+            assert!(is_ancestor(&mut conn, &a, &c, 2).await.unwrap());
+            assert!(!is_ancestor(&mut conn, &a, &c, 1).await.unwrap());
+        });
+    }
+
+    #[test]
+    fn a_referral_loop_is_refused() {
+        block_on(async {
+            let chain = referral_chain(2).await;
+            let (a, b) = (chain[0], chain[1]);
+
+            assert_eq!(
+                set_referrer(&a, &b, Some(64)).await.unwrap(),
+                ReferralLink::Cycle
+            );
+            assert_eq!(
+                set_referrer(&a, &a, Some(64)).await.unwrap(),
+                ReferralLink::SelfReferral
+            );
+            let referrer = sqlx::query_scalar!("SELECT referrer_id FROM users WHERE id = $1", a)
+                .fetch_one(&*DB_POOL)
+                .await
+                .unwrap();
+            assert_eq!(referrer, None);
+        });
+    }
+
+    #[test]
+    fn a_fresh_referral_links_once() {
+        block_on(async {
+            let referee = create_user("default", &random_wallet()).await.unwrap();
+            let first = create_user("default", &random_wallet()).await.unwrap();
+            let second = create_user("default", &random_wallet()).await.unwrap();
+
+            assert_eq!(
+                set_referrer(&referee, &first, Some(64)).await.unwrap(),
+                ReferralLink::Linked
+            );
+            assert_eq!(
+                set_referrer(&referee, &second, Some(64)).await.unwrap(),
+                ReferralLink::AlreadyReferred
+            );
+        });
+    }
+
+    #[test]
+    fn referral_credit_stops_at_the_cap() {
+        // 100 points for a fresh referee, under a cap of 250
+        async fn credit(conn: &mut PgConnection, referrer: &Uuid, reason: &str) -> i32 {
+            let referee = Uuid::new_v4().to_string();
+            credit_referral(conn, referrer, 100, reason, &referee, Some(250))
+                .await
+                .unwrap()
+        }
+
+        block_on(async {
+            let wallet = user_with_points(0).await;
+            let referrer = create_user("default", &wallet).await.unwrap();
+            let mut conn = DB_POOL.acquire().await.unwrap();
+
+            assert_eq!(credit(&mut conn, &referrer, "referral").await, 100);
+            assert_eq!(credit(&mut conn, &referrer, "referral_tier2").await, 100);
+            // Cut down to exactly the cap, then nothing past it
+            assert_eq!(credit(&mut conn, &referrer, "referral").await, 50);
+            assert_eq!(credit(&mut conn, &referrer, "referral").await, 0);
+            assert_eq!(points_of(&wallet).await, 250);
+        });
+    }
+
+    #[test]
+    fn a_campaign_only_pays_out_for_its_own_project() {
+        block_on(async {
+            let (running, other) = (random_project().await, random_project().await);
+            sqlx::query!(
+                "INSERT INTO campaigns (project_id, name, token_mint, airdrop_amount, is_active)
+                 VALUES ($1, 'Launch', $2, 500, TRUE)",
+                running,
+                random_wallet()
+            )
+            .execute(&*DB_POOL)
+            .await
+            .unwrap();
+
+            assert!(get_active_campaign(&running).await.unwrap().is_some());
+            assert!(get_active_campaign(&other).await.unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn submitting_a_proof_reports_why_it_was_refused() {
+        block_on(async {
+            let project = random_project().await;
+            let wallet = random_wallet();
+            create_user(&project, &wallet).await.unwrap();
+            let task = create_task(&project, "Reviewed", 10, None, None)
+                .await
+                .unwrap();
+
+            assert!(matches!(
+                submit_task_proof(&project, &random_wallet(), task.id, "proof").await,
+                Err(TaskError::UserNotFound)
+            ));
+            assert!(matches!(
+                submit_task_proof(&project, &wallet, Uuid::new_v4(), "proof").await,
+                Err(TaskError::TaskNotFound)
+            ));
+            submit_task_proof(&project, &wallet, task.id, "proof")
+                .await
+                .unwrap();
+            assert!(matches!(
+                submit_task_proof(&project, &wallet, task.id, "again").await,
+                Err(TaskError::AlreadySubmitted)
+            ));
+        });
+    }
+}
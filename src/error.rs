@@ -1,15 +1,62 @@
+use crate::telemetry;
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
     Json,
 };
+use serde::Serialize;
 use serde_json::json;
 use std::fmt;
 
+// Stable machine-readable error codes for clients to branch on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    InvalidRequest,
+    MissingParameter,
+    InvalidWallet,
+    WalletNotFound,
+    ProjectNotFound,
+    NotInBeta,
+    ReferralNotFound,
+    SelfReferral,
+    ReferralCycle,
+    Unauthorized,
+    InvalidSignature,
+    NonceExpired,
+    InvalidDisplayName,
+    DisplayNameTaken,
+    TaskUnavailable,
+    TaskInactive,
+    TaskAlreadyCompleted,
+    SubmissionPending,
+    AlreadyClaimed,
+    OnchainConditionNotMet,
+    InsufficientPoints,
+    ClaimNotYetAvailable,
+    ClaimsPaused,
+    ClaimPending,
+    RegionRestricted,
+    FeeNotDetected,
+    FeeAlreadyUsed,
+    TransferFailed,
+    RecipientFrozen,
+    BlockhashExpired,
+    TreasuryDepleted,
+    MintNotAllowed,
+    RpcError,
+    DatabaseError,
+    ConfigError,
+    ServerBusy,
+    RateLimited,
+    InternalError,
+}
+
 #[derive(Debug)]
 pub struct AppError {
     pub status: StatusCode,
     pub message: String,
+    pub code: Option<ErrorCode>,
 }
 
 impl AppError {
@@ -17,8 +64,14 @@ impl AppError {
         AppError {
             status,
             message: msg.into(),
+            code: None,
         }
     }
+
+    pub fn with_code(mut self, code: ErrorCode) -> Self {
+        self.code = Some(code);
+        self
+    }
 }
 
 impl fmt::Display for AppError {
@@ -31,7 +84,14 @@ impl std::error::Error for AppError {}
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let body = Json(json!({ "error": self.message }));
-        (self.status, body).into_response()
+        let mut body = match self.code {
+            Some(code) => json!({ "error": self.message, "code": code }),
+            None => json!({ "error": self.message }),
+        };
+        // Lets a user's bug report be matched to the server logs
+        if let Some(id) = telemetry::current_request_id() {
+            body["request_id"] = json!(id);
+        }
+        (self.status, Json(body)).into_response()
     }
 }
@@ -0,0 +1,72 @@
+use crate::config::CONFIG;
+use crate::models::user::public_wallet;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::{collections::VecDeque, sync::Mutex};
+use tokio::sync::{broadcast, watch};
+
+// Slow subscribers that fall further behind than this just miss events
+const CHANNEL_CAPACITY: usize = 256;
+
+static AIRDROP_EVENTS: Lazy<broadcast::Sender<AirdropEvent>> =
+    Lazy::new(|| broadcast::channel(CHANNEL_CAPACITY).0);
+
+// The last SSE_REPLAY_SIZE events, oldest first. Publishing and subscribing
+// both hold this lock, so a new subscriber sees every event exactly once:
+// either in its replay or on its receiver.
+static RECENT_AIRDROPS: Lazy<Mutex<VecDeque<AirdropEvent>>> =
+    Lazy::new(|| Mutex::new(VecDeque::with_capacity(CONFIG.sse_replay_size)));
+
+// Flipped once on shutdown so open SSE streams end instead of holding the
+// graceful shutdown open until its timeout
+static SHUTTING_DOWN: Lazy<watch::Sender<bool>> = Lazy::new(|| watch::channel(false).0);
+
+#[derive(Clone, Serialize)]
+pub struct AirdropEvent {
+    pub wallet: String,
+    pub amount: i64,
+    #[serde(serialize_with = "crate::models::timestamp::rfc3339")]
+    pub timestamp: DateTime<Utc>,
+}
+
+impl AirdropEvent {
+    pub fn new(wallet: &str, amount: i64) -> Self {
+        AirdropEvent {
+            wallet: public_wallet(wallet),
+            amount,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+// Announce a successful claim to SSE subscribers
+pub fn publish_airdrop(event: AirdropEvent) {
+    let mut recent = RECENT_AIRDROPS.lock().unwrap();
+    if CONFIG.sse_replay_size > 0 {
+        if recent.len() == CONFIG.sse_replay_size {
+            recent.pop_front();
+        }
+        recent.push_back(event.clone());
+    }
+    // Err only means nobody is listening right now
+    let _ = AIRDROP_EVENTS.send(event);
+}
+
+// Recent events (ordered by timestamp) to replay, plus a receiver for
+// everything published after them
+pub fn subscribe_airdrops() -> (Vec<AirdropEvent>, broadcast::Receiver<AirdropEvent>) {
+    let recent = RECENT_AIRDROPS.lock().unwrap();
+    let mut replay: Vec<AirdropEvent> = recent.iter().cloned().collect();
+    // Concurrent claims can publish slightly out of timestamp order
+    replay.sort_by_key(|e| e.timestamp);
+    (replay, AIRDROP_EVENTS.subscribe())
+}
+
+pub fn close_streams() {
+    SHUTTING_DOWN.send_replace(true);
+}
+
+pub fn subscribe_shutdown() -> watch::Receiver<bool> {
+    SHUTTING_DOWN.subscribe()
+}
@@ -0,0 +1,109 @@
+use crate::{
+    config::CONFIG,
+    error::{AppError, ErrorCode},
+};
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use maxminddb::{geoip2, Reader};
+use once_cell::sync::OnceCell;
+use std::net::{IpAddr, SocketAddr};
+
+static GEOIP: OnceCell<Reader<Vec<u8>>> = OnceCell::new();
+
+// Load the country database named by GEOIP_DB_PATH. A configured but
+// unreadable database is fatal, since starting without it would silently
+// lift the block.
+pub fn init() {
+    let Some(path) = CONFIG.geoip_db_path.as_deref() else {
+        return;
+    };
+    let reader = Reader::open_readfile(path)
+        .unwrap_or_else(|e| panic!("Failed to open GEOIP_DB_PATH {}: {}", path, e));
+    tracing::info!(
+        path,
+        blocked = %CONFIG.blocked_countries.join(","),
+        "GeoIP database loaded"
+    );
+    let _ = GEOIP.set(reader);
+}
+
+// Route middleware rejecting requests from BLOCKED_COUNTRIES with 451. The IP
+// is only used for the lookup; it is never logged or stored. Addresses the
+// database doesn't know are let through.
+pub async fn block_restricted(req: Request, next: Next) -> Response {
+    if let (Some(reader), Some(ip)) = (GEOIP.get(), client_ip(&req)) {
+        if let Some(country) = country_code(reader, ip) {
+            if CONFIG.blocked_countries.contains(&country) {
+                tracing::info!(%country, "Rejected request from restricted region");
+                return AppError::new(
+                    StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS,
+                    "This service is not available in your region",
+                )
+                .with_code(ErrorCode::RegionRestricted)
+                .into_response();
+            }
+        }
+    }
+    next.run(req).await
+}
+
+// The X-Forwarded-For entry TRUSTED_PROXY_HOPS from the right when trusted,
+// else the socket peer
+pub fn client_ip(req: &Request) -> Option<IpAddr> {
+    if CONFIG.trust_forwarded_for {
+        let forwarded = req
+            .headers()
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| forwarded_client(v, CONFIG.trusted_proxy_hops));
+        if forwarded.is_some() {
+            return forwarded;
+        }
+    }
+    req.extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+}
+
+// Each proxy appends the address it got the request from, so only the last
+// `hops` entries were written by our proxies. A header shorter than that
+// didn't come through all of them and is ignored.
+fn forwarded_client(header: &str, hops: usize) -> Option<IpAddr> {
+    header
+        .rsplit(',')
+        .nth(hops.checked_sub(1)?)?
+        .trim()
+        .parse()
+        .ok()
+}
+
+fn country_code(reader: &Reader<Vec<u8>>, ip: IpAddr) -> Option<String> {
+    let country: geoip2::Country = reader.lookup(ip).ok()?;
+    country
+        .country
+        .and_then(|c| c.iso_code)
+        .map(str::to_ascii_uppercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_client_is_taken_from_the_right_of_the_header() {
+        // A client claiming to be 1.1.1.1, behind one proxy that saw 2.2.2.2
+        let header = "1.1.1.1, 2.2.2.2";
+        assert_eq!(forwarded_client(header, 1), "2.2.2.2".parse().ok());
+        assert_eq!(forwarded_client(header, 2), "1.1.1.1".parse().ok());
+    }
+
+    #[test]
+    fn a_header_shorter_than_the_proxy_chain_is_ignored() {
+        assert_eq!(forwarded_client("2.2.2.2", 2), None);
+        assert_eq!(forwarded_client("not-an-ip", 1), None);
+    }
+}
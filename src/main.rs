@@ -1,38 +1,237 @@
 mod api;
+mod ata;
+mod auth;
 mod config;
 mod db;
+mod error;
+mod events;
+mod geo;
 mod models;
+mod notify;
+mod points_log;
+mod rate_limit;
+mod reconcile;
 mod solana;
-mod error;
+mod telemetry;
+#[cfg(test)]
+mod testing;
+mod verify;
+mod webhook;
 
-use axum::Router;
+use axum::{
+    error_handling::HandleErrorLayer,
+    extract::Request,
+    http::{header, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::Response,
+    BoxError, Router,
+};
 use dotenvy::dotenv;
+use error::{AppError, ErrorCode};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpListener;
+use tokio::sync::Notify;
+use tower::{limit::ConcurrencyLimitLayer, load_shed::LoadShedLayer, ServiceBuilder};
 use tower_http::cors::{Any, CorsLayer};
-use tracing_subscriber;
 
-#[tokio::main]
-async fn main() {
+// Requests rejected because the server was at MAX_CONCURRENT_REQUESTS
+static SHED_REQUESTS: AtomicU64 = AtomicU64::new(0);
+
+async fn handle_overload(err: BoxError) -> AppError {
+    if err.is::<tower::load_shed::error::Overloaded>() {
+        let total = SHED_REQUESTS.fetch_add(1, Ordering::Relaxed) + 1;
+        metrics::counter!("http_requests_shed_total").increment(1);
+        tracing::warn!(shed_total = total, "Shedding request: server at capacity");
+        AppError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Server is busy, try again shortly",
+        )
+        .with_code(ErrorCode::ServerBusy)
+    } else {
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Unhandled internal error",
+        )
+        .with_code(ErrorCode::InternalError)
+    }
+}
+
+static X_API_VERSION: HeaderName = HeaderName::from_static("x-api-version");
+
+// Stamp every response, errors and shed requests included, with the API
+// version. Clients may send `Accept-Version`; only one shape exists today, so
+// it isn't consulted yet, but this is where it would be matched.
+async fn api_version_header(req: Request, next: Next) -> Response {
+    let mut res = next.run(req).await;
+    if let Ok(value) = HeaderValue::from_str(&config::CONFIG.api_version) {
+        res.headers_mut().insert(X_API_VERSION.clone(), value);
+    }
+    res
+}
+
+fn main() {
     dotenv().ok();
     tracing_subscriber::fmt::init();
 
+    // Built by hand instead of #[tokio::main] so small containers can cap the
+    // thread counts rather than getting one worker per host CPU
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    builder.enable_all();
+    if let Some(n) = config::CONFIG.worker_threads {
+        builder.worker_threads(n);
+    }
+    if let Some(n) = config::CONFIG.max_blocking_threads {
+        builder.max_blocking_threads(n);
+    }
+    let runtime = builder.build().expect("Failed to build tokio runtime");
+
+    runtime.block_on(run());
+}
+
+async fn run() {
+    if config::CONFIG.bypass_points_check {
+        tracing::warn!("⚠️ BYPASS_POINTS_CHECK enabled: claims skip the minimum points check. Never use this on mainnet.");
+    }
+
+    telemetry::init();
+
+    let decimals = tokio::task::spawn_blocking(solana::preload_mint_decimals)
+        .await
+        .unwrap_or(None);
+    config::CONFIG.log_summary(decimals);
+
+    geo::init();
     db::init_db().await.expect("Database failed");
+    webhook::start();
+    points_log::start();
+    notify::init();
+    db::start_points_sync();
+    db::start_wallet_count_refresh();
+    ata::start();
+    reconcile::start();
 
-    // Configure CORS
-    let cors = CorsLayer::new()
-        .allow_origin(Any) // You can replace Any with a specific origin
-        .allow_methods(Any)
-        .allow_headers(Any);
+    let cors = cors_layer();
 
-    let app = Router::new()
+    let mut routes = Router::new()
+        .merge(api::health::routes())
         .merge(api::user::routes())
         .merge(api::tasks::routes())
-        .layer(cors); // Add the CORS layer here
+        .merge(api::events::routes())
+        .merge(api::leaderboard::routes())
+        .merge(api::referral::routes())
+        .merge(api::stats::routes())
+        .merge(api::admin::routes());
+
+    if config::CONFIG.dev_endpoints {
+        tracing::warn!("DEV_ENDPOINTS enabled: mounting /api/dev routes");
+        routes = routes.merge(api::dev::routes());
+    }
 
-    let listener = TcpListener::bind("0.0.0.0:8080").await.unwrap();
+    let app = routes
+        .layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(handle_overload))
+                .layer(LoadShedLayer::new())
+                .layer(ConcurrencyLimitLayer::new(
+                    config::CONFIG.max_concurrent_requests,
+                )),
+        )
+        // Outside the load shedding, so SERVER_BUSY responses carry CORS
+        // headers too and browsers can read them
+        .layer(cors)
+        .layer(middleware::from_fn(telemetry::track_requests))
+        .layer(middleware::from_fn(api::cache::no_store_by_default))
+        .layer(middleware::from_fn(api_version_header))
+        .layer(middleware::from_fn(telemetry::request_context));
+
+    let listener = TcpListener::bind(config::BIND_ADDR).await.unwrap();
     println!("🚀 Server running at http://localhost:8080");
 
-    axum::serve(listener, app.into_make_service())
-        .await
-        .unwrap();
+    // Peer addresses are needed for geoblocking. On SIGTERM/SIGINT the
+    // listener closes and in-flight requests get SHUTDOWN_TIMEOUT to finish.
+    let shutdown_started = Arc::new(Notify::new());
+    let server = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown({
+        let shutdown_started = shutdown_started.clone();
+        async move {
+            shutdown_signal().await;
+            tracing::info!("Shutdown signal received; draining in-flight requests");
+            events::close_streams();
+            shutdown_started.notify_one();
+        }
+    });
+
+    let timeout = Duration::from_secs(config::CONFIG.shutdown_timeout_secs);
+    tokio::select! {
+        result = server => result.expect("Server error"),
+        _ = async {
+            shutdown_started.notified().await;
+            tokio::time::sleep(timeout).await;
+        } => tracing::warn!(
+            timeout_secs = timeout.as_secs(),
+            "Shutdown timed out; dropping requests still in flight"
+        ),
+    }
+
+    // Don't lose notifications or ledger entries still waiting to be written
+    webhook::shutdown().await;
+    points_log::shutdown().await;
+    tracing::info!("Shutdown complete");
+}
+
+// Only the methods and headers the API uses. Origins come from
+// CORS_ALLOWED_ORIGINS (validated at startup), or any origin when unset.
+fn cors_layer() -> CorsLayer {
+    let cors = CorsLayer::new()
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::AUTHORIZATION,
+            HeaderName::from_static("x-project-id"),
+            HeaderName::from_static("x-request-id"),
+            HeaderName::from_static("accept-version"),
+        ])
+        .expose_headers([
+            HeaderName::from_static("x-request-id"),
+            X_API_VERSION.clone(),
+        ]);
+
+    match &config::CONFIG.cors_allowed_origins {
+        Some(origins) => cors.allow_origin(
+            origins
+                .iter()
+                .map(|o| HeaderValue::from_str(o).expect("Invalid CORS origin"))
+                .collect::<Vec<_>>(),
+        ),
+        None => cors.allow_origin(Any),
+    }
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for SIGINT");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
 }
@@ -0,0 +1,21 @@
+// A transfer that was sent but not confirmed before the claim stopped
+// waiting; see reconcile.rs
+pub struct PendingAirdrop {
+    pub signature: String,
+    pub project_id: String,
+    pub wallet_address: String,
+    pub mint: String,
+    pub amount: i64,
+    // Deducted once the transfer lands
+    pub points: i32,
+    pub fee_tx: Option<String>,
+    // The transfer can't land once the chain is past this height
+    pub last_valid_block_height: i64,
+}
+
+// A wallet waiting for the ATA worker to create its token account for the
+// project's mint; see ata.rs
+pub struct PendingAtaCreation {
+    pub project_id: String,
+    pub wallet_address: String,
+}
@@ -0,0 +1,19 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+pub struct AuthNonce {
+    pub nonce: Uuid,
+    pub wallet_address: String,
+    pub used: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+// What a client needs to ask the wallet for a sign-in signature
+#[derive(Serialize)]
+pub struct SignInChallenge {
+    pub nonce: Uuid,
+    pub message: String,
+    #[serde(serialize_with = "crate::models::timestamp::rfc3339")]
+    pub expires_at: DateTime<Utc>,
+}
@@ -0,0 +1,11 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+#[derive(Serialize)]
+pub struct Campaign {
+    pub id: Uuid,
+    pub name: String,
+    pub token_mint: String,
+    pub airdrop_amount: i32,
+    pub is_active: bool,
+}
@@ -0,0 +1,17 @@
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct ReferralLeaderboardEntry {
+    pub rank: i64,
+    pub wallet: String,
+    pub display_name: String,
+    pub referrals: i64,
+}
+
+#[derive(Serialize)]
+pub struct PointsLeaderboardEntry {
+    pub rank: i64,
+    pub wallet: String,
+    pub display_name: String,
+    pub total_points: i32,
+}
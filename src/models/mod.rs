@@ -1,2 +1,9 @@
+pub mod airdrop;
+pub mod auth;
+pub mod campaign;
+pub mod leaderboard;
+pub mod project;
+pub mod stats;
 pub mod task;
+pub mod timestamp;
 pub mod user;
@@ -0,0 +1,61 @@
+use crate::config::{AirdropPayout, CONFIG};
+
+// One airdrop project. Settings left NULL fall back to the deployment-wide
+// config, so the 'default' project behaves exactly like a single-tenant setup.
+#[derive(Clone)]
+pub struct Project {
+    pub id: String,
+    pub token_mint: Option<String>,
+    pub required_fee_lamports: Option<i64>,
+    pub min_claim_points: Option<i32>,
+    pub airdrop_tokens: Option<i64>,
+}
+
+impl Project {
+    pub fn fee_lamports(&self) -> u64 {
+        self.required_fee_lamports
+            .map(|l| l as u64)
+            .unwrap_or(CONFIG.required_fee_lamports)
+    }
+
+    pub fn claim_points(&self) -> i32 {
+        self.min_claim_points.unwrap_or(CONFIG.min_claim_points)
+    }
+
+    // Tokens per claim, in UI units
+    pub fn claim_tokens(&self) -> i64 {
+        self.airdrop_tokens.unwrap_or(CONFIG.airdrop_tokens)
+    }
+
+    // What a claim sends and what it costs, for a wallet holding total_points.
+    // fixed_tokens is the campaign's amount when one applies, otherwise
+    // claim_tokens(). The caller checks tokens is positive before sending.
+    pub fn compute_airdrop_amount(&self, fixed_tokens: i64, total_points: i32) -> AirdropAmount {
+        let balance = total_points.max(0);
+        match CONFIG.airdrop_payout {
+            AirdropPayout::Fixed => AirdropAmount {
+                tokens: fixed_tokens,
+                // BYPASS_POINTS_CHECK lets a wallet claim with fewer points,
+                // so it is charged what it has
+                points: if CONFIG.bypass_points_check {
+                    self.claim_points().min(balance)
+                } else {
+                    self.claim_points()
+                },
+            },
+            AirdropPayout::PerPoint => AirdropAmount {
+                tokens: (balance as i64).saturating_mul(CONFIG.airdrop_tokens_per_point),
+                points: balance,
+            },
+        }
+    }
+}
+
+// Result of Project::compute_airdrop_amount. The same value is sent, held in
+// pending_airdrops and deducted, so the two can't drift apart.
+#[derive(Clone, Copy, Debug)]
+pub struct AirdropAmount {
+    // UI units; send_tokens scales them by the mint's decimals
+    pub tokens: i64,
+    pub points: i32,
+}
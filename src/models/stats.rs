@@ -0,0 +1,11 @@
+use serde::Serialize;
+
+#[derive(Serialize, Clone, Copy, Default)]
+pub struct PointsTotals {
+    // Points currently held across all users
+    pub circulating: i64,
+    // Every point ever credited: task completions plus positive ledger entries
+    pub awarded: i64,
+    // Every point ever taken back: claims and negative admin adjustments
+    pub deducted: i64,
+}
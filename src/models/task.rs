@@ -1,4 +1,5 @@
-use serde::Serialize;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 #[derive(Serialize)]
@@ -7,4 +8,84 @@ pub struct Task {
     pub name: String,
     pub points: i32,
     pub description: Option<String>,
+    // Grouping for the frontend, e.g. "social"; "general" when not set
+    pub category: String,
+    // Completing it needs a proof that an admin approves
+    pub requires_review: bool,
+    // Still completable after claiming, for zero points
+    pub post_claim_allowed: bool,
+}
+
+// One entry of TASKS_SEED_JSON
+#[derive(Deserialize)]
+pub struct TaskSeed {
+    pub name: String,
+    pub points: i32,
+    pub description: Option<String>,
+    pub category: Option<String>,
+}
+
+// How a completion of a task gets checked: an optional on-chain condition,
+// or manual review of a submitted proof
+pub struct TaskVerification {
+    // Deactivated by an admin; completions are refused
+    pub is_active: bool,
+    pub requires_review: bool,
+    pub verify_type: Option<String>,
+    pub verify_mint: Option<String>,
+    pub verify_min_amount: Option<i64>,
+}
+
+// One of a wallet's completed tasks, for /api/user/completed_tasks
+#[derive(Serialize)]
+pub struct CompletedTask {
+    pub id: Uuid,
+    pub name: String,
+    // What the wallet was awarded, which is 0 for tasks done after claiming
+    pub points: i32,
+    // Unknown for some early completions
+    #[serde(serialize_with = "crate::models::timestamp::rfc3339_opt")]
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+// Result of db::complete_task
+pub struct TaskCompletion {
+    pub points_awarded: i32,
+    // The user's balance afterwards, as /api/user/points would report it
+    pub total_points: i32,
+}
+
+// Why db::complete_task or db::submit_task_proof refused a completion
+#[derive(Debug)]
+pub enum TaskError {
+    UserNotFound,
+    // Missing, retired or in another project
+    TaskNotFound,
+    // Claimed wallets only earn from post_claim_allowed tasks
+    AlreadyClaimed,
+    AlreadyCompleted,
+    // A proof for the task is already awaiting review
+    AlreadySubmitted,
+    Db(sqlx::Error),
+}
+
+impl From<sqlx::Error> for TaskError {
+    fn from(e: sqlx::Error) -> Self {
+        TaskError::Db(e)
+    }
+}
+
+// A proof awaiting (or past) admin review
+#[derive(Serialize)]
+pub struct TaskSubmission {
+    pub id: Uuid,
+    pub wallet: String,
+    pub task_id: Uuid,
+    pub task_name: String,
+    pub proof: String,
+    pub status: String,
+    #[serde(serialize_with = "crate::models::timestamp::rfc3339")]
+    pub created_at: DateTime<Utc>,
+    #[serde(serialize_with = "crate::models::timestamp::rfc3339_opt")]
+    pub reviewed_at: Option<DateTime<Utc>>,
 }
@@ -0,0 +1,19 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serializer;
+
+// Every timestamp in a response goes out as RFC3339 UTC with second precision,
+// e.g. "2025-06-01T12:00:00Z". Use via
+// `#[serde(serialize_with = "crate::models::timestamp::rfc3339")]`.
+pub fn rfc3339<S: Serializer>(dt: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&dt.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+pub fn rfc3339_opt<S: Serializer>(
+    dt: &Option<DateTime<Utc>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match dt {
+        Some(dt) => rfc3339(dt, serializer),
+        None => serializer.serialize_none(),
+    }
+}
@@ -1,3 +1,5 @@
+use crate::config::{PublicWalletDisplay, CONFIG};
+use chrono::{DateTime, Utc};
 use serde::Serialize;
 use uuid::Uuid;
 
@@ -8,4 +10,80 @@ pub struct UserInfo {
     pub tasks_completed: Vec<Uuid>,
     pub referrals: i64,
     pub has_claimed: bool, // ✅ Add this line
+    pub display_name: String,
+}
+
+// A wallet as shown in public responses (leaderboards, live feed), per
+// PUBLIC_WALLET_DISPLAY. Admin and the wallet's own responses use the full address.
+pub fn public_wallet(wallet: &str) -> String {
+    match CONFIG.public_wallet_display {
+        PublicWalletDisplay::Truncated => truncate_wallet(wallet),
+        PublicWalletDisplay::Full => wallet.to_string(),
+    }
+}
+
+// Short public form of a wallet address, e.g. "DkrC...ERBT"
+pub fn truncate_wallet(wallet: &str) -> String {
+    let chars: Vec<char> = wallet.chars().collect();
+    if chars.len() <= 8 {
+        return wallet.to_string();
+    }
+    let head: String = chars[..4].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}...{}", head, tail)
+}
+
+// One change to a user's points, as shown by /api/user/points_history
+#[derive(Serialize)]
+pub struct PointHistoryEntry {
+    pub delta: i32,
+    // 'task', 'referral', 'referral_tier2', 'claim' or 'admin: <reason>'
+    pub reason: String,
+    // Task id for 'task', referee id for 'referral' and 'referral_tier2',
+    // transfer signature for 'claim'; none for admin adjustments
+    pub reference_id: Option<String>,
+    #[serde(serialize_with = "crate::models::timestamp::rfc3339")]
+    pub created_at: DateTime<Utc>,
+}
+
+// Result of db::set_referrer
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReferralLink {
+    Linked,
+    // Referrers are set once; reconnecting with another code changes nothing
+    AlreadyReferred,
+    SelfReferral,
+    Cycle,
+}
+
+// Why db::finalize_claim didn't record a claim
+#[derive(Debug)]
+pub enum ClaimError {
+    // The balance dropped below the claim's points after it was checked
+    InsufficientPoints,
+    // Another claim already burned the fee
+    FeeAlreadyUsed,
+    Db(sqlx::Error),
+}
+
+impl From<sqlx::Error> for ClaimError {
+    fn from(e: sqlx::Error) -> Self {
+        ClaimError::Db(e)
+    }
+}
+
+impl std::fmt::Display for ClaimError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClaimError::InsufficientPoints => write!(f, "not enough points to deduct"),
+            ClaimError::FeeAlreadyUsed => write!(f, "fee already used"),
+            ClaimError::Db(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+// Where to send a user's claim receipt
+pub struct ClaimContact {
+    pub contact_email: Option<String>,
+    pub contact_webhook_url: Option<String>,
 }
@@ -0,0 +1,153 @@
+use crate::{
+    config::CONFIG,
+    events::{self, AirdropEvent},
+    webhook,
+};
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use uuid::Uuid;
+
+// Something worth telling the outside world about. Handlers publish these
+// and never care which transports are listening.
+#[derive(Clone)]
+pub enum Event {
+    AirdropClaimed {
+        project: String,
+        wallet: String,
+        amount: i64,
+        mint: String,
+        tx: String,
+    },
+    TaskCompleted {
+        wallet: String,
+        task_id: Uuid,
+        points: i32,
+    },
+}
+
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn notify(&self, event: Event);
+}
+
+// Used when NOTIFIERS configures nothing
+pub struct NoopNotifier;
+
+#[async_trait]
+impl Notifier for NoopNotifier {
+    async fn notify(&self, _event: Event) {}
+}
+
+// Live airdrop feed for /api/events/airdrops
+pub struct SseNotifier;
+
+#[async_trait]
+impl Notifier for SseNotifier {
+    async fn notify(&self, event: Event) {
+        if let Event::AirdropClaimed { wallet, amount, .. } = event {
+            events::publish_airdrop(AirdropEvent::new(&wallet, amount));
+        }
+    }
+}
+
+// Batched public announcement to WEBHOOK_URL
+pub struct WebhookNotifier;
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn notify(&self, event: Event) {
+        if let Event::AirdropClaimed { wallet, amount, .. } = event {
+            webhook::enqueue(AirdropEvent::new(&wallet, amount));
+        }
+    }
+}
+
+// Per-user claim receipt for users who opted in (NOTIFY_ON_CLAIM)
+pub struct ReceiptNotifier;
+
+#[async_trait]
+impl Notifier for ReceiptNotifier {
+    async fn notify(&self, event: Event) {
+        if let Event::AirdropClaimed {
+            project,
+            wallet,
+            amount,
+            mint,
+            tx,
+        } = event
+        {
+            webhook::send_claim_receipt(&project, &wallet, amount, &mint, &tx);
+        }
+    }
+}
+
+pub struct LogNotifier;
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    async fn notify(&self, event: Event) {
+        match event {
+            Event::AirdropClaimed {
+                wallet, amount, tx, ..
+            } => tracing::info!(%wallet, amount, %tx, "Airdrop claimed"),
+            Event::TaskCompleted {
+                wallet,
+                task_id,
+                points,
+            } => tracing::info!(%wallet, %task_id, points, "Task completed"),
+        }
+    }
+}
+
+// Fans each event out to every configured notifier, in order
+pub struct CompositeNotifier {
+    notifiers: Vec<Box<dyn Notifier>>,
+}
+
+impl CompositeNotifier {
+    // NOTIFIERS is a comma-separated list of sse, webhook, receipt and log
+    fn from_config() -> Self {
+        let notifiers: Vec<Box<dyn Notifier>> = CONFIG
+            .notifiers
+            .iter()
+            .map(|name| -> Box<dyn Notifier> {
+                match name.as_str() {
+                    "sse" => Box::new(SseNotifier),
+                    "webhook" => Box::new(WebhookNotifier),
+                    "receipt" => Box::new(ReceiptNotifier),
+                    "log" => Box::new(LogNotifier),
+                    other => panic!("Unknown notifier in NOTIFIERS: {}", other),
+                }
+            })
+            .collect();
+
+        if notifiers.is_empty() {
+            return CompositeNotifier {
+                notifiers: vec![Box::new(NoopNotifier)],
+            };
+        }
+        CompositeNotifier { notifiers }
+    }
+}
+
+#[async_trait]
+impl Notifier for CompositeNotifier {
+    async fn notify(&self, event: Event) {
+        for notifier in &self.notifiers {
+            notifier.notify(event.clone()).await;
+        }
+    }
+}
+
+static NOTIFIER: Lazy<CompositeNotifier> = Lazy::new(CompositeNotifier::from_config);
+
+// Build the notifier set up front so a bad NOTIFIERS fails at startup
+pub fn init() {
+    Lazy::force(&NOTIFIER);
+}
+
+// Fire-and-forget: delivery runs on its own task and never holds up or
+// fails the request that produced the event
+pub fn publish(event: Event) {
+    tokio::spawn(async move { NOTIFIER.notify(event).await });
+}
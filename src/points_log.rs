@@ -0,0 +1,122 @@
+use crate::{config::CONFIG, db};
+use sqlx::PgConnection;
+use std::{sync::Mutex, time::Duration};
+use tokio::{sync::mpsc, task::JoinHandle};
+use uuid::Uuid;
+
+// One row of the point_events ledger
+pub struct PointEvent {
+    pub user_id: Uuid,
+    pub delta: i32,
+    pub reason: String,
+    pub reference_id: Option<String>,
+}
+
+// Dropping the sender is what tells the worker to flush and stop
+static SENDER: Mutex<Option<mpsc::UnboundedSender<PointEvent>>> = Mutex::new(None);
+static WORKER: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+
+// Ledger entries for one transaction. By default each entry is written on the
+// transaction as it is recorded. With POINTS_LOG_ASYNC they are held until
+// `commit` (called after the transaction commits) and then written in
+// batches by the background worker, so a rolled back transaction leaves no
+// entries behind. Entries still queued when the process dies are lost.
+#[derive(Default)]
+pub struct Ledger {
+    pending: Vec<PointEvent>,
+}
+
+impl Ledger {
+    pub async fn record(
+        &mut self,
+        conn: &mut PgConnection,
+        event: PointEvent,
+    ) -> Result<(), sqlx::Error> {
+        if CONFIG.points_log_async {
+            self.pending.push(event);
+            Ok(())
+        } else {
+            db::record_point_event(
+                conn,
+                &event.user_id,
+                event.delta,
+                &event.reason,
+                event.reference_id.as_deref(),
+            )
+            .await
+        }
+    }
+
+    pub fn commit(self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        match SENDER.lock().unwrap().as_ref() {
+            Some(tx) => {
+                for event in self.pending {
+                    let _ = tx.send(event);
+                }
+            }
+            None => tracing::warn!(
+                count = self.pending.len(),
+                "Points log worker not running; dropping ledger entries"
+            ),
+        }
+    }
+}
+
+// Start the batching worker if POINTS_LOG_ASYNC is set
+pub fn start() {
+    if !CONFIG.points_log_async {
+        return;
+    }
+    let interval = Duration::from_millis(CONFIG.points_log_flush_ms);
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    *SENDER.lock().unwrap() = Some(tx);
+    *WORKER.lock().unwrap() = Some(tokio::spawn(run(interval, rx)));
+}
+
+// Flush whatever is buffered and wait for the worker to exit
+pub async fn shutdown() {
+    SENDER.lock().unwrap().take();
+    let worker = WORKER.lock().unwrap().take();
+    if let Some(worker) = worker {
+        let _ = worker.await;
+    }
+}
+
+// Writes the buffer every `interval`, or sooner once POINTS_LOG_BATCH_SIZE
+// entries are waiting
+async fn run(interval: Duration, mut rx: mpsc::UnboundedReceiver<PointEvent>) {
+    let mut batch = Vec::new();
+    let mut ticker = tokio::time::interval(interval);
+
+    loop {
+        tokio::select! {
+            msg = rx.recv() => match msg {
+                Some(event) => {
+                    batch.push(event);
+                    if batch.len() >= CONFIG.points_log_batch_size {
+                        flush(&mut batch).await;
+                    }
+                }
+                None => {
+                    flush(&mut batch).await;
+                    break;
+                }
+            },
+            _ = ticker.tick() => flush(&mut batch).await,
+        }
+    }
+}
+
+async fn flush(batch: &mut Vec<PointEvent>) {
+    if batch.is_empty() {
+        return;
+    }
+    if let Err(e) = db::insert_point_events(batch).await {
+        tracing::error!(error = %e, count = batch.len(), "Failed to write points log batch");
+    }
+    batch.clear();
+}
@@ -0,0 +1,120 @@
+use crate::{
+    config::CONFIG,
+    error::{AppError, ErrorCode},
+    geo,
+};
+use axum::{
+    extract::Request,
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+// A bucket refills completely within this long, so one untouched for longer
+// is indistinguishable from a fresh one and can be dropped
+const WINDOW: Duration = Duration::from_secs(60);
+
+// Task completions per wallet (TASK_RATE_LIMIT_PER_MINUTE)
+pub static TASK_WALLET_LIMITER: Lazy<RateLimiter> =
+    Lazy::new(|| RateLimiter::new(CONFIG.task_rate_limit_per_minute));
+// Task completions per client IP (TASK_RATE_LIMIT_PER_IP)
+static TASK_IP_LIMITER: Lazy<RateLimiter> =
+    Lazy::new(|| RateLimiter::new(CONFIG.task_rate_limit_per_ip.unwrap_or(0)));
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+// In-memory token buckets: up to `per_minute` requests in a burst per key,
+// refilled continuously at `per_minute` a minute. Per instance, so behind a
+// load balancer the effective limit is multiplied by the instance count.
+pub struct RateLimiter {
+    per_minute: u32,
+    buckets: Mutex<HashMap<String, Bucket>>,
+    last_prune: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    // 0 disables the limiter
+    pub fn new(per_minute: u32) -> Self {
+        RateLimiter {
+            per_minute,
+            buckets: Mutex::new(HashMap::new()),
+            last_prune: Mutex::new(Instant::now()),
+        }
+    }
+
+    // Take a token for `key`, or say how long until one is available
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        if self.per_minute == 0 {
+            return Ok(());
+        }
+        let capacity = self.per_minute as f64;
+        let refill_per_sec = capacity / WINDOW.as_secs_f64();
+        let now = Instant::now();
+
+        self.prune(now);
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / refill_per_sec,
+            ))
+        }
+    }
+
+    // At most once a window, forget keys that have been idle for a full window
+    fn prune(&self, now: Instant) {
+        let mut last_prune = self.last_prune.lock().unwrap();
+        if now.duration_since(*last_prune) < WINDOW {
+            return;
+        }
+        *last_prune = now;
+        self.buckets
+            .lock()
+            .unwrap()
+            .retain(|_, b| now.duration_since(b.updated) < WINDOW);
+    }
+}
+
+pub fn too_many_requests(retry_after: Duration) -> AppError {
+    AppError::new(
+        StatusCode::TOO_MANY_REQUESTS,
+        format!(
+            "Too many requests, retry in {}s",
+            retry_after.as_secs().max(1)
+        ),
+    )
+    .with_code(ErrorCode::RateLimited)
+}
+
+// Route layer for complete_task's per-IP limit. The per-wallet limit is
+// applied in the handler, since the wallet is in the JSON body.
+pub async fn limit_tasks_by_ip(req: Request, next: Next) -> Response {
+    if CONFIG.task_rate_limit_per_ip.is_some() {
+        if let Some(ip) = geo::client_ip(&req) {
+            if let Err(retry_after) = TASK_IP_LIMITER.check(&ip.to_string()) {
+                return too_many_requests(retry_after).into_response();
+            }
+        }
+    }
+    next.run(req).await
+}
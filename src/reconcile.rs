@@ -0,0 +1,114 @@
+use crate::{
+    api,
+    config::CONFIG,
+    db,
+    models::{airdrop::PendingAirdrop, user::ClaimError},
+    solana,
+};
+use solana::SignatureState;
+use std::time::Duration;
+
+// Pending transfers checked per tick
+const BATCH_SIZE: i64 = 50;
+
+// Background job settling transfers a claim stopped waiting for
+// (pending_airdrops). One that landed is recorded as the claim it was; one
+// that failed, or whose blockhash expired without it landing, is dropped and
+// its fee released so the wallet can claim again with the same fee. Anything
+// else waits for the next tick.
+pub fn start() {
+    let interval = Duration::from_millis(CONFIG.reconcile_interval_ms);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = run_batch().await {
+                tracing::warn!(error = %e, "Pending airdrop reconciliation failed");
+            }
+        }
+    });
+}
+
+async fn run_batch() -> Result<(), sqlx::Error> {
+    for pending in db::get_pending_airdrops(BATCH_SIZE).await? {
+        let Ok(_permit) = solana::RPC_PERMITS.acquire().await else {
+            return Ok(());
+        };
+
+        let (sig, height) = (
+            pending.signature.clone(),
+            pending.last_valid_block_height as u64,
+        );
+        let state = tokio::task::spawn_blocking(move || solana::check_transfer(&sig, height))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r.map_err(|e| e.to_string()));
+
+        match state {
+            Ok(SignatureState::Landed(_)) => settle(&pending).await?,
+            Ok(SignatureState::Failed(err)) => {
+                tracing::warn!(tx = %pending.signature, wallet = %pending.wallet_address, error = ?err, "Pending airdrop failed on chain");
+                db::drop_pending_airdrop(&pending).await?;
+            }
+            Ok(SignatureState::Dropped) => {
+                tracing::warn!(tx = %pending.signature, wallet = %pending.wallet_address, "Pending airdrop expired without landing");
+                db::drop_pending_airdrop(&pending).await?;
+            }
+            Ok(SignatureState::InFlight) => {}
+            Err(e) => {
+                tracing::warn!(tx = %pending.signature, error = %e, "Could not check pending airdrop")
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn settle(pending: &PendingAirdrop) -> Result<(), sqlx::Error> {
+    let recorded = db::finalize_claim(
+        &pending.project_id,
+        &pending.wallet_address,
+        pending.amount,
+        &pending.signature,
+        pending.fee_tx.as_deref(),
+        pending.points,
+    )
+    .await;
+    match recorded {
+        Ok(_) => {}
+        // Left pending for an operator: the fee this transfer was held
+        // against has been burned by another claim. (The points were taken
+        // when the claim was reserved, so finalize_claim doesn't refuse for
+        // those.)
+        Err(e @ (ClaimError::FeeAlreadyUsed | ClaimError::InsufficientPoints)) => {
+            tracing::error!(
+                wallet = %pending.wallet_address,
+                tx = %pending.signature,
+                fee_tx = pending.fee_tx.as_deref().unwrap_or("none"),
+                error = %e,
+                "Pending airdrop landed but the claim could not be recorded"
+            );
+            return Ok(());
+        }
+        Err(ClaimError::Db(e)) => {
+            tracing::error!(
+                wallet = %pending.wallet_address,
+                tx = %pending.signature,
+                error = %e,
+                "Pending airdrop landed but recording the claim failed"
+            );
+            return Err(e);
+        }
+    }
+
+    tracing::info!(wallet = %pending.wallet_address, tx = %pending.signature, "Pending airdrop confirmed");
+    api::user::claim_recorded(
+        &pending.project_id,
+        &pending.wallet_address,
+        pending.amount,
+        &pending.mint,
+        &pending.signature,
+    )
+    .await;
+    Ok(())
+}
@@ -1,140 +1,1132 @@
-use crate::error::AppError;
+// Results here carry solana_client's ClientError, which is large but is what
+// every RpcClient call returns
+#![allow(clippy::result_large_err)]
+
+use crate::auth;
+use crate::config::CONFIG;
+use crate::error::{AppError, ErrorCode};
+use crate::telemetry;
 use axum::http::StatusCode;
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Deserializer, Serialize};
+use solana_client::client_error::{ClientError, ClientErrorKind};
 use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_client::rpc_request::RpcError;
+use solana_client::rpc_response::RpcConfirmedTransactionStatusWithSignature;
 use solana_sdk::{
     commitment_config::CommitmentConfig,
-    instruction::Instruction,
+    compute_budget::ComputeBudgetInstruction,
+    instruction::{Instruction, InstructionError},
+    program_utils::limited_deserialize,
     pubkey::Pubkey,
-    signature::{read_keypair_file, Signature, Signer},
-    transaction::Transaction,
+    signature::{read_keypair_file, Keypair, Signature, Signer},
+    system_instruction::SystemInstruction,
+    system_program,
+    transaction::{Transaction, TransactionError},
 };
 use solana_transaction_status::{
-    EncodedTransaction, UiMessage, UiParsedMessage, UiTransaction, UiTransactionEncoding,
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, TransactionConfirmationStatus,
+    UiInnerInstructions, UiInstruction, UiLoadedAddresses, UiMessage, UiParsedInstruction,
+    UiTransactionEncoding,
 };
 use spl_associated_token_account::get_associated_token_address;
+use spl_token::error::TokenError;
 use spl_token::instruction::transfer_checked;
 use spl_token::ID as TOKEN_PROGRAM_ID;
-use std::{env, str::FromStr};
+use std::{collections::HashMap, env, str::FromStr, sync::Mutex};
+use uuid::Uuid;
 
-const REQUIRED_LAMPORTS: u64 = 6_000; // 0.006 SOL
-const TOKEN_DECIMALS: u8 = 6;
+// Shared by claims and background jobs (ATA pre-creation) so together they
+// stay within RPC_MAX_CONCURRENCY requests' worth of load on the node
+pub static RPC_PERMITS: Lazy<tokio::sync::Semaphore> =
+    Lazy::new(|| tokio::sync::Semaphore::new(CONFIG.rpc_max_concurrency));
 
-pub async fn check_fee_paid(user_wallet: &str) -> Result<Option<String>, AppError> {
-    let rpc_url = env::var("SOLANA_RPC_URL")
-        .map_err(|_| AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Missing SOLANA_RPC_URL"))?;
+// Mint decimals never change, so each mint is only looked up once
+static MINT_DECIMALS: Lazy<Mutex<HashMap<Pubkey, u8>>> = Lazy::new(|| Mutex::new(HashMap::new()));
 
-    let rpc = RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed());
+// Signatures fetched per page when scanning for a fee payment
+const FEE_SCAN_PAGE_SIZE: usize = 100;
+// Checkpoints are dropped wholesale past this many wallets
+const MAX_FEE_SCAN_CHECKPOINTS: usize = 10_000;
 
-    let user_pubkey = Pubkey::from_str(user_wallet)
-        .map_err(|_| AppError::new(StatusCode::BAD_REQUEST, "Invalid user wallet"))?;
+// Per (wallet, required fee): the newest signature from which every older one
+// in the window has already been checked and isn't that wallet's payment. A
+// retry after "Fee not detected" stops there instead of re-checking them.
+static FEE_SCAN_CHECKPOINTS: Lazy<Mutex<HashMap<(Pubkey, u64), Signature>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
 
-    let airdrop_wallet = Pubkey::from_str("DkrCNNn27B1Loz6eGpMYKAL7b5J4GY6wwQs8wqY9ERBT")
-        .map_err(|_| AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Invalid airdrop wallet"))?;
+// Strip whitespace and stray URL-encoding (e.g. a double-encoded query
+// param) from a wallet address before it is validated or looked up
+pub fn normalize_wallet(wallet: &str) -> String {
+    let decoded = percent_decode_str(wallet.trim()).decode_utf8_lossy();
+    decoded.trim().to_string()
+}
 
-    let sigs = rpc
-        .get_signatures_for_address_with_config(
-            &airdrop_wallet,
-            GetConfirmedSignaturesForAddress2Config {
-                limit: Some(50),
-                ..Default::default()
-            },
+// serde `deserialize_with` helper applying normalize_wallet to request bodies
+pub fn deserialize_wallet<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let raw = String::deserialize(deserializer)?;
+    Ok(normalize_wallet(&raw))
+}
+
+// Parse a (normalized) wallet address, rejecting anything that isn't a
+// valid base58 public key with a 400. Handlers call this first so junk never
+// reaches the database or the RPC.
+pub fn validate_wallet(wallet: &str) -> Result<Pubkey, AppError> {
+    Pubkey::from_str(wallet).map_err(|_| {
+        AppError::new(StatusCode::BAD_REQUEST, "Invalid wallet address")
+            .with_code(ErrorCode::InvalidWallet)
+    })
+}
+
+// Check that `signature` (base58) is the wallet's signature over the sign-in
+// message for `nonce`, as issued by /api/user/login_challenge, and burn the
+// nonce. The message is rebuilt server-side; see auth::verify_wallet_signature.
+pub async fn verify_signed_message(
+    wallet: &str,
+    nonce: &Uuid,
+    signature: &str,
+) -> Result<(), AppError> {
+    auth::verify_wallet_signature(wallet, nonce, signature).await
+}
+
+// Mint used when no campaign is active
+pub fn default_mint() -> Result<String, AppError> {
+    env::var("TOKEN_MINT").map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Missing TOKEN_MINT")
+            .with_code(ErrorCode::ConfigError)
+    })
+}
+
+// The treasury keypair: AIR_DROP_WALLET_KEY (a base58 secret key or a JSON
+// byte array, as written by solana-keygen) when set, else the file at
+// AIR_DROP_WALLET_PATH. Errors never include the key itself.
+pub fn load_payer() -> Result<Keypair, AppError> {
+    let config_error = |msg: &str| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, msg).with_code(ErrorCode::ConfigError)
+    };
+
+    if let Ok(key) = env::var("AIR_DROP_WALLET_KEY") {
+        let key = key.trim();
+        let bytes = if key.starts_with('[') {
+            serde_json::from_str::<Vec<u8>>(key).ok()
+        } else {
+            solana_sdk::bs58::decode(key).into_vec().ok()
+        };
+        return bytes
+            .and_then(|b| Keypair::from_bytes(&b).ok())
+            .ok_or_else(|| config_error("AIR_DROP_WALLET_KEY is not a valid keypair"));
+    }
+
+    let path = env::var("AIR_DROP_WALLET_PATH")
+        .map_err(|_| config_error("Missing AIR_DROP_WALLET_KEY or AIR_DROP_WALLET_PATH"))?;
+    read_keypair_file(path).map_err(|_| config_error("Failed to load wallet keypair"))
+}
+
+// Optional separate keypair paying tx fees and ATA rent (FEE_PAYER_WALLET_PATH)
+fn load_fee_payer() -> Result<Option<Keypair>, AppError> {
+    let Ok(path) = env::var("FEE_PAYER_WALLET_PATH") else {
+        return Ok(None);
+    };
+    read_keypair_file(path).map(Some).map_err(|_| {
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to load fee payer keypair",
         )
+        .with_code(ErrorCode::ConfigError)
+    })
+}
+
+// Tokens of one mint held by the treasury
+#[derive(Serialize)]
+pub struct TreasuryBalance {
+    pub mint: String,
+    // Base units
+    pub amount: u64,
+    pub ui_amount: String,
+    pub decimals: u8,
+}
+
+// The treasury's balance of `mint`, as send_tokens checks it before a transfer
+pub fn treasury_balance(mint: &str) -> Result<TreasuryBalance, AppError> {
+    let rpc_url = env::var("SOLANA_RPC_URL").map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Missing SOLANA_RPC_URL")
+            .with_code(ErrorCode::ConfigError)
+    })?;
+    let rpc = RpcClient::new(rpc_url);
+
+    let payer = load_payer()?;
+    let mint_key = Pubkey::from_str(mint).map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Invalid mint address")
+            .with_code(ErrorCode::ConfigError)
+    })?;
+
+    let account = get_associated_token_address(&payer.pubkey(), &mint_key);
+    token_account_balance(&rpc, &account, &mint_key)
+}
+
+fn token_account_balance(
+    rpc: &RpcClient,
+    account: &Pubkey,
+    mint: &Pubkey,
+) -> Result<TreasuryBalance, AppError> {
+    let balance = with_retry("get_token_account_balance", || {
+        rpc.get_token_account_balance(account)
+    })
+    .map_err(|e| {
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to fetch treasury token balance: {}", e),
+        )
+        .with_code(ErrorCode::RpcError)
+    })?;
+    Ok(TreasuryBalance {
+        mint: mint.to_string(),
+        amount: balance.amount.parse().unwrap_or(0),
+        ui_amount: balance.ui_amount_string,
+        decimals: balance.decimals,
+    })
+}
+
+// Decimals of the given mint, read from the chain on first use
+pub fn mint_decimals(rpc: &RpcClient, mint: &Pubkey) -> Result<u8, AppError> {
+    if let Some(decimals) = MINT_DECIMALS.lock().unwrap().get(mint) {
+        return Ok(*decimals);
+    }
+
+    let decimals = with_retry("get_token_supply", || rpc.get_token_supply(mint))
+        .map_err(|_| {
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to fetch mint decimals",
+            )
+            .with_code(ErrorCode::RpcError)
+        })?
+        .decimals;
+
+    MINT_DECIMALS.lock().unwrap().insert(*mint, decimals);
+    Ok(decimals)
+}
+
+// UI tokens -> base units for a mint with `decimals`, e.g. 1000 tokens at
+// 6 decimals is 1_000_000_000. None on overflow.
+pub fn ui_to_raw(ui_amount: u64, decimals: u8) -> Option<u64> {
+    10u64
+        .checked_pow(decimals as u32)
+        .and_then(|scale| ui_amount.checked_mul(scale))
+}
+
+// What the chain recorded for a confirmed transaction
+pub struct TxConfirmation {
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub fee_lamports: Option<u64>,
+    pub status: Option<String>,
+}
+
+// Slot, block time, fee and commitment level of a sent transaction. Transient
+// RPC failures are retried like any other read.
+pub fn fetch_confirmation(sig: &str) -> Result<TxConfirmation, AppError> {
+    let rpc_url = env::var("SOLANA_RPC_URL").map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Missing SOLANA_RPC_URL")
+            .with_code(ErrorCode::ConfigError)
+    })?;
+    let rpc = RpcClient::new(rpc_url);
+    let sig = Signature::from_str(sig).map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Invalid signature")
+            .with_code(ErrorCode::RpcError)
+    })?;
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Json),
+        commitment: Some(CommitmentConfig::confirmed()),
+        max_supported_transaction_version: Some(0),
+    };
+
+    let tx = with_retry("get_transaction", || {
+        rpc.get_transaction_with_config(&sig, config)
+    })
+    .map_err(|e| {
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to fetch transaction: {}", e),
+        )
+        .with_code(ErrorCode::RpcError)
+    })?;
+
+    // Status is best effort; the transaction itself is the important part
+    let status = rpc
+        .get_signature_statuses(&[sig])
+        .ok()
+        .and_then(|res| res.value.into_iter().next().flatten())
+        .and_then(|s| s.confirmation_status)
+        .map(|s| format!("{:?}", s).to_ascii_lowercase());
+
+    Ok(TxConfirmation {
+        slot: tx.slot,
+        block_time: tx.block_time,
+        fee_lamports: tx.transaction.meta.map(|m| m.fee),
+        status,
+    })
+}
+
+// Look up the default mint's decimals at startup so a bad TOKEN_MINT or RPC
+// shows up in the logs right away rather than on the first claim
+pub fn preload_mint_decimals() -> Option<u8> {
+    let mint = match default_mint().map(|m| Pubkey::from_str(&m)) {
+        Ok(Ok(mint)) => mint,
+        _ => {
+            tracing::warn!("TOKEN_MINT missing or invalid");
+            return None;
+        }
+    };
+    let Ok(rpc_url) = env::var("SOLANA_RPC_URL") else {
+        tracing::warn!("SOLANA_RPC_URL missing");
+        return None;
+    };
+
+    match mint_decimals(&RpcClient::new(rpc_url), &mint) {
+        Ok(decimals) => {
+            tracing::info!(%mint, decimals, "Loaded mint decimals");
+            Some(decimals)
+        }
+        Err(e) => {
+            tracing::warn!(%mint, error = %e, "Could not resolve mint decimals");
+            None
+        }
+    }
+}
+
+// Run an RPC call, retrying transient failures up to RPC_MAX_RETRIES times.
+// Delays double from RPC_RETRY_BASE_DELAY_MS (capped at 10s), each cut by a
+// random amount up to half so concurrent claims don't retry in lockstep.
+// Blocking, like the RpcClient calls it wraps, and it sleeps between
+// attempts: async code reaches it only through `blocking` or spawn_blocking.
+pub fn with_retry<T>(
+    op: &str,
+    mut call: impl FnMut() -> Result<T, ClientError>,
+) -> Result<T, ClientError> {
+    let mut attempt = 0;
+    loop {
+        match call() {
+            Err(e) if attempt < CONFIG.rpc_max_retries && is_retryable(&e) => {
+                let delay = retry_delay(attempt);
+                attempt += 1;
+                tracing::warn!(
+                    op,
+                    attempt,
+                    error = %e,
+                    retry_in_ms = delay.as_millis() as u64,
+                    "RPC call failed, retrying"
+                );
+                std::thread::sleep(delay);
+            }
+            result => return result,
+        }
+    }
+}
+
+fn retry_delay(attempt: u32) -> std::time::Duration {
+    use std::hash::{BuildHasher, Hasher};
+
+    let full = CONFIG
+        .rpc_retry_base_delay_ms
+        .saturating_mul(1 << attempt.min(16))
+        .min(10_000);
+    // RandomState is seeded per instance, which is all the randomness needed
+    let random = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    std::time::Duration::from_millis(full - random % (full / 2 + 1))
+}
+
+// Failures worth another try: the node was unreachable, slow, rate limiting
+// us or briefly unhealthy. Anything the node answered definitively (a bad
+// request, a missing account, a rejected transaction) fails the same way again.
+fn is_retryable(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::Io(_) => true,
+        ClientErrorKind::Reqwest(e) => {
+            e.is_timeout()
+                || e.is_connect()
+                || e.status()
+                    .is_some_and(|s| s.as_u16() == 429 || s.is_server_error())
+        }
+        ClientErrorKind::RpcError(RpcError::RpcRequestError(_)) => true,
+        // -32005: node is behind; -32603: internal error
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            matches!(code, -32005 | -32603)
+        }
+        _ => false,
+    }
+}
+
+// `ixs` preceded by the compute budget instructions PRIORITY_FEE_MICROLAMPORTS
+// and COMPUTE_UNIT_LIMIT ask for, so our transactions still land under
+// congestion. With neither set, `ixs` is returned unchanged.
+fn with_compute_budget(ixs: Vec<Instruction>) -> Vec<Instruction> {
+    let limit = CONFIG
+        .compute_unit_limit
+        .map(ComputeBudgetInstruction::set_compute_unit_limit);
+    let price = (CONFIG.priority_fee_microlamports > 0).then(|| {
+        ComputeBudgetInstruction::set_compute_unit_price(CONFIG.priority_fee_microlamports)
+    });
+    limit.into_iter().chain(price).chain(ixs).collect()
+}
+
+// True when the cluster rejected the tx because its blockhash is gone
+fn is_blockhash_expired(err: &ClientError) -> bool {
+    matches!(
+        err.get_transaction_error(),
+        Some(TransactionError::BlockhashNotFound)
+    )
+}
+
+// How far a submitted transfer got
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferStatus {
+    Confirmed,
+    Finalized,
+    // Still unconfirmed when TRANSFER_CONFIRM_TIMEOUT_SECS ran out. It can
+    // land until the chain passes `last_valid_block_height`.
+    Pending { last_valid_block_height: u64 },
+}
+
+impl TransferStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransferStatus::Confirmed => "confirmed",
+            TransferStatus::Finalized => "finalized",
+            TransferStatus::Pending { .. } => "pending",
+        }
+    }
+}
+
+pub struct Transfer {
+    pub signature: String,
+    pub status: TransferStatus,
+}
+
+// What the chain says about a submitted transaction
+pub enum SignatureState {
+    Landed(TransferStatus),
+    Failed(TransactionError),
+    // Never landed and its blockhash has expired, so it never will
+    Dropped,
+    // Not landed (or only processed) yet, and still could
+    InFlight,
+}
+
+// Where `sig` stands. Searches history, so this also works for transfers
+// submitted long ago.
+pub fn signature_state(
+    rpc: &RpcClient,
+    sig: &Signature,
+    last_valid_block_height: u64,
+) -> Result<SignatureState, ClientError> {
+    // Read before the status: if the blockhash had already expired by then
+    // and there's still no status, the tx can't have landed in between
+    let block_height = with_retry("get_block_height", || rpc.get_block_height())?;
+    let status = with_retry("get_signature_statuses", || {
+        rpc.get_signature_statuses_with_history(&[*sig])
+    })?
+    .value
+    .into_iter()
+    .next()
+    .flatten();
+
+    if let Some(status) = status {
+        if let Some(err) = status.err {
+            return Ok(SignatureState::Failed(err));
+        }
+        match status.confirmation_status {
+            Some(TransactionConfirmationStatus::Finalized) => {
+                return Ok(SignatureState::Landed(TransferStatus::Finalized))
+            }
+            Some(TransactionConfirmationStatus::Confirmed) => {
+                return Ok(SignatureState::Landed(TransferStatus::Confirmed))
+            }
+            // Processed only: could still be on a fork that gets dropped
+            _ => {}
+        }
+    }
+
+    Ok(if block_height > last_valid_block_height {
+        SignatureState::Dropped
+    } else {
+        SignatureState::InFlight
+    })
+}
+
+// signature_state for a stored signature, for the reconciler
+pub fn check_transfer(sig: &str, last_valid_block_height: u64) -> Result<SignatureState, AppError> {
+    let rpc_url = env::var("SOLANA_RPC_URL").map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Missing SOLANA_RPC_URL")
+            .with_code(ErrorCode::ConfigError)
+    })?;
+    let sig = Signature::from_str(sig).map_err(|_| {
+        AppError::new(StatusCode::BAD_REQUEST, "Invalid transaction signature")
+            .with_code(ErrorCode::InvalidRequest)
+    })?;
+    let rpc = RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed());
+    signature_state(&rpc, &sig, last_valid_block_height).map_err(|e| {
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to fetch transfer status: {}", e),
+        )
+        .with_code(ErrorCode::RpcError)
+    })
+}
+
+// Poll `sig` until it lands, fails or drops, or TRANSFER_CONFIRM_TIMEOUT_SECS
+// passes (InFlight). The tx has been sent by now, so a failed poll is only
+// logged: reporting the transfer as failed could get it sent twice. Sleeps
+// between polls, so only call it off the async workers (see `blocking`).
+fn await_signature(
+    rpc: &RpcClient,
+    sig: &Signature,
+    last_valid_block_height: u64,
+) -> SignatureState {
+    let deadline = std::time::Instant::now()
+        + std::time::Duration::from_secs(CONFIG.transfer_confirm_timeout_secs);
+    loop {
+        match signature_state(rpc, sig, last_valid_block_height) {
+            Ok(SignatureState::InFlight) => {}
+            Ok(state) => return state,
+            Err(e) => tracing::warn!(%sig, error = %e, "Could not poll transfer status"),
+        }
+        if std::time::Instant::now() >= deadline {
+            return SignatureState::InFlight;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(500));
+    }
+}
+
+// True when the token program refused the transfer because an account
+// (the recipient's, typically under a default-frozen mint) is frozen
+fn is_account_frozen(err: &ClientError) -> bool {
+    err.get_transaction_error()
+        .is_some_and(|e| is_frozen_tx_error(&e))
+}
+
+fn is_frozen_tx_error(err: &TransactionError) -> bool {
+    matches!(
+        err,
+        TransactionError::InstructionError(_, InstructionError::Custom(code))
+            if *code == TokenError::AccountFrozen as u32
+    )
+}
+
+// Whether at least `min` slots have passed since `tx_slot`, counting the slot
+// it landed in as zero
+fn has_confirmations(current_slot: u64, tx_slot: u64, min: u64) -> bool {
+    current_slot.saturating_sub(tx_slot) >= min
+}
+
+// Whether a payment made at `block_time` is older than `max_age_seconds`
+// allows; one exactly that old still counts
+fn too_old(now: i64, block_time: i64, max_age_seconds: u64) -> bool {
+    now - block_time > max_age_seconds as i64
+}
+
+// A system program transfer as (source, destination, lamports), from any of
+// the forms the node returns an instruction in. `accounts` resolves the
+// indexes of a compiled instruction.
+fn system_transfer(
+    ix: &UiInstruction,
+    accounts: &[(String, bool)],
+) -> Option<(String, String, u64)> {
+    let system_id = system_program::id().to_string();
+
+    let (program_id, ix_accounts, data) = match ix {
+        UiInstruction::Parsed(UiParsedInstruction::Parsed(parsed)) => {
+            if parsed.program_id != system_id || parsed.parsed["type"] != "transfer" {
+                return None;
+            }
+            let info = &parsed.parsed["info"];
+            return Some((
+                info["source"].as_str()?.to_string(),
+                info["destination"].as_str()?.to_string(),
+                info["lamports"].as_u64()?,
+            ));
+        }
+        UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(ix)) => {
+            (ix.program_id.clone(), ix.accounts.clone(), &ix.data)
+        }
+        UiInstruction::Compiled(ix) => {
+            let key = |i: &u8| accounts.get(*i as usize).map(|(k, _)| k.clone());
+            let ix_accounts = ix.accounts.iter().map(key).collect::<Option<Vec<_>>>()?;
+            (key(&ix.program_id_index)?, ix_accounts, &ix.data)
+        }
+    };
+    if program_id != system_id {
+        return None;
+    }
+
+    let data = solana_sdk::bs58::decode(data).into_vec().ok()?;
+    match limited_deserialize(&data).ok()? {
+        SystemInstruction::Transfer { lamports } => Some((
+            ix_accounts.first()?.clone(),
+            ix_accounts.get(1)?.clone(),
+            lamports,
+        )),
+        _ => None,
+    }
+}
+
+// Whether a transaction is the user paying the fee: it contains system
+// transfers (top level or inner) from the claiming wallet to the airdrop wallet
+// totalling at least `required` lamports, the claiming wallet signed it, the
+// airdrop wallet didn't (so it isn't a self-transfer or one of our own
+// payouts), and the net balance changes agree: the user lost and the airdrop
+// wallet gained at least `required`. Merely appearing in a transaction someone
+// else paid for is not enough. The airdrop wallet's delta is net of any tx
+// fee, which only applies when it signs, so a non-signing airdrop wallet sees
+// the full amount.
+fn is_fee_payment(
+    accounts: &[(String, bool)],
+    instructions: &[&UiInstruction],
+    pre_balances: &[u64],
+    post_balances: &[u64],
+    user: &str,
+    airdrop: &str,
+    required: u64,
+) -> bool {
+    if user == airdrop {
+        return false;
+    }
+
+    let delta = |idx: usize| -> Option<i64> {
+        Some(*post_balances.get(idx)? as i64 - *pre_balances.get(idx)? as i64)
+    };
+
+    let Some(user_idx) = accounts.iter().position(|(k, _)| k == user) else {
+        return false;
+    };
+    let Some(airdrop_idx) = accounts.iter().position(|(k, _)| k == airdrop) else {
+        return false;
+    };
+
+    let user_signed = accounts[user_idx].1;
+    let airdrop_signed = accounts[airdrop_idx].1;
+    if !user_signed || airdrop_signed {
+        return false;
+    }
+
+    let transferred: u64 = instructions
+        .iter()
+        .filter_map(|ix| system_transfer(ix, accounts))
+        .filter(|(source, destination, _)| source == user && destination == airdrop)
+        .map(|(_, _, lamports)| lamports)
+        .fold(0, u64::saturating_add);
+    if transferred < required {
+        return false;
+    }
+
+    let required = required as i64;
+    matches!(
+        (delta(user_idx), delta(airdrop_idx)),
+        (Some(paid), Some(received)) if paid <= -required && received >= required
+    )
+}
+
+// The RpcClient blocks (and so do with_retry's backoff and await_signature's
+// polling), so async callers run RPC work through here instead of on a tokio
+// worker
+pub async fn blocking<T, F>(f: F) -> Result<T, AppError>
+where
+    F: FnOnce() -> Result<T, AppError> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await.map_err(|e| {
+        tracing::error!(error = %e, "Blocking RPC task failed");
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "RPC task failed")
+            .with_code(ErrorCode::InternalError)
+    })?
+}
+
+// Signature of a payment of at least `required_lamports` from the wallet to
+// the airdrop wallet, if there is one
+pub async fn check_fee_paid(
+    user_wallet: &str,
+    required_lamports: u64,
+) -> Result<Option<String>, AppError> {
+    let user_wallet = user_wallet.to_string();
+    blocking(move || scan_fee_payments(&user_wallet, required_lamports)).await
+}
+
+fn scan_fee_payments(
+    user_wallet: &str,
+    required_lamports: u64,
+) -> Result<Option<String>, AppError> {
+    let _timer = telemetry::RpcTimer::start("check_fee_paid");
+    let rpc_url = env::var("SOLANA_RPC_URL").map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Missing SOLANA_RPC_URL")
+            .with_code(ErrorCode::ConfigError)
+    })?;
+
+    let rpc = RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed());
+
+    let user_pubkey = validate_wallet(user_wallet)?;
+
+    // Fees are paid to the treasury that sends the airdrops
+    let airdrop_wallet = load_payer()?.pubkey();
+
+    find_fee_payment(&rpc, &user_pubkey, &airdrop_wallet, required_lamports)
+}
+
+// The RPC calls a fee scan makes. RpcClient is the real thing; the tests
+// stand in a canned history.
+trait FeeScanRpc {
+    fn slot(&self) -> Result<u64, ClientError>;
+    fn signatures(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>, ClientError>;
+    fn transaction(
+        &self,
+        sig: &Signature,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta, ClientError>;
+}
+
+impl FeeScanRpc for RpcClient {
+    fn slot(&self) -> Result<u64, ClientError> {
+        self.get_slot()
+    }
+
+    fn signatures(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>, ClientError> {
+        self.get_signatures_for_address_with_config(address, config)
+    }
+
+    fn transaction(
+        &self,
+        sig: &Signature,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta, ClientError> {
+        // Without max_supported_transaction_version the node refuses to
+        // return v0 transactions at all
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::JsonParsed),
+            commitment: Some(CommitmentConfig::confirmed()),
+            max_supported_transaction_version: Some(0),
+        };
+        self.get_transaction_with_config(sig, config)
+    }
+}
+
+fn find_fee_payment(
+    rpc: &impl FeeScanRpc,
+    user_pubkey: &Pubkey,
+    airdrop_wallet: &Pubkey,
+    required_lamports: u64,
+) -> Result<Option<String>, AppError> {
+    let checkpoint_key = (*user_pubkey, required_lamports);
+    let until = FEE_SCAN_CHECKPOINTS
+        .lock()
+        .unwrap()
+        .get(&checkpoint_key)
+        .copied();
+
+    let now = Utc::now().timestamp();
+
+    // Only needed when payments must have aged a number of slots
+    let current_slot = if CONFIG.fee_min_confirmations > 0 {
+        Some(with_retry("get_slot", || rpc.slot()).map_err(|_| {
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to fetch current slot",
+            )
+            .with_code(ErrorCode::RpcError)
+        })?)
+    } else {
+        None
+    };
+
+    // Pages back from the newest signature until the payment turns up, the
+    // checkpoint or the end of history is reached, or FEE_SCAN_MAX_SIGNATURES
+    // have been looked at
+    let mut before = None;
+    let mut scanned = 0;
+    // Newest signature with nothing skipped below it, the next checkpoint
+    let mut checked_from: Option<Signature> = None;
+
+    'pages: while scanned < CONFIG.fee_scan_max_signatures {
+        let limit = FEE_SCAN_PAGE_SIZE.min(CONFIG.fee_scan_max_signatures - scanned);
+        let sigs = with_retry("get_signatures_for_address", || {
+            rpc.signatures(
+                airdrop_wallet,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until,
+                    limit: Some(limit),
+                    ..Default::default()
+                },
+            )
+        })
         .map_err(|_| {
             AppError::new(
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Failed to fetch transactions",
             )
+            .with_code(ErrorCode::RpcError)
         })?;
+        scanned += sigs.len();
+        let last_page = sigs.len() < limit;
+        before = sigs
+            .last()
+            .and_then(|s| Signature::from_str(&s.signature).ok());
 
-    for sig_info in sigs {
-        let sig = Signature::from_str(&sig_info.signature)
-            .map_err(|_| AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Invalid signature"))?;
+        for sig_info in sigs {
+            // Too recent to trust yet; it may still be dropped. The user can
+            // retry once it has aged.
+            if let Some(current_slot) = current_slot {
+                if !has_confirmations(current_slot, sig_info.slot, CONFIG.fee_min_confirmations) {
+                    checked_from = None;
+                    continue;
+                }
+            }
 
-        let tx = rpc
-            .get_transaction(&sig, UiTransactionEncoding::JsonParsed)
-            .map_err(|_| {
-                AppError::new(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Failed to fetch transaction",
-                )
-            })?;
+            // Only fresh payments count. Signatures come newest first, so once
+            // one is too old the rest, and every older page, are too.
+            if let Some(max_age) = CONFIG.fee_max_age_seconds {
+                match sig_info.block_time {
+                    Some(block_time) if too_old(now, block_time, max_age) => break 'pages,
+                    Some(_) => {}
+                    // Without a block time we can't prove it's recent
+                    None => {
+                        checked_from = None;
+                        continue;
+                    }
+                }
+            }
 
-        if let Some(meta) = tx.transaction.meta {
-            if let EncodedTransaction::Json(json_tx) = tx.transaction.transaction {
-                let pubkeys: Vec<String> = match &json_tx.message {
-                    UiMessage::Parsed(parsed_msg) => parsed_msg
+            // One malformed or undecodable transaction shouldn't fail the
+            // whole scan; skip it and keep looking for the payment
+            let Ok(sig) = Signature::from_str(&sig_info.signature) else {
+                tracing::debug!(signature = %sig_info.signature, "Skipping unparseable signature");
+                continue;
+            };
+
+            let tx = match with_retry("get_transaction", || rpc.transaction(&sig)) {
+                Ok(tx) => tx,
+                Err(e) => {
+                    tracing::debug!(%sig, error = %e, "Skipping transaction");
+                    checked_from = None;
+                    continue;
+                }
+            };
+
+            let Some(meta) = tx.transaction.meta else {
+                continue;
+            };
+            // A failed tx still moves the fee, but never the payment
+            if meta.err.is_some() {
+                continue;
+            }
+            let EncodedTransaction::Json(json_tx) = tx.transaction.transaction else {
+                continue;
+            };
+
+            // (pubkey, is_signer) for every account in the message, in the same
+            // order as the balance arrays
+            let accounts: Vec<(String, bool)> = match &json_tx.message {
+                UiMessage::Parsed(parsed_msg) => parsed_msg
+                    .account_keys
+                    .iter()
+                    .map(|acc| (acc.pubkey.clone(), acc.signer))
+                    .collect(),
+                // Parsed messages already list lookup-table accounts; raw v0
+                // messages only carry the static keys, with the rest in the meta
+                // (writable first, then readonly). Loaded accounts never sign.
+                UiMessage::Raw(raw_msg) => {
+                    let loaded: Option<UiLoadedAddresses> = meta.loaded_addresses.clone().into();
+                    raw_msg
                         .account_keys
                         .iter()
-                        .map(|acc| acc.pubkey.clone())
-                        .collect(),
-                    UiMessage::Raw(raw_msg) => raw_msg.account_keys.clone(),
-                };
-
-                let s_airdrop_wallet = airdrop_wallet.to_string();
-                if let Ok(airdrop_pubkey) = Pubkey::from_str(&s_airdrop_wallet) {
-                    if let Some(idx) = pubkeys
-                        .iter()
-                        .position(|k| Pubkey::from_str(k).unwrap() == airdrop_pubkey)
-                    {
-                        let delta = meta.post_balances[idx] as i64 - meta.pre_balances[idx] as i64;
-
-                        if delta >= REQUIRED_LAMPORTS as i64
-                            && pubkeys
-                                .iter()
-                                .any(|k| Pubkey::from_str(k).unwrap() == user_pubkey)
-                        {
-                            return Ok(Some(sig.to_string()));
-                        }
-                    }
+                        .enumerate()
+                        .map(|(i, k)| {
+                            (
+                                k.clone(),
+                                i < raw_msg.header.num_required_signatures as usize,
+                            )
+                        })
+                        .chain(
+                            loaded
+                                .into_iter()
+                                .flat_map(|l| l.writable.into_iter().chain(l.readonly))
+                                .map(|k| (k, false)),
+                        )
+                        .collect()
                 }
+            };
+
+            // Inner instructions catch a payment made through another program
+            let top_level: Vec<UiInstruction> = match &json_tx.message {
+                UiMessage::Parsed(parsed_msg) => parsed_msg.instructions.clone(),
+                UiMessage::Raw(raw_msg) => raw_msg
+                    .instructions
+                    .iter()
+                    .cloned()
+                    .map(UiInstruction::Compiled)
+                    .collect(),
+            };
+            let inner: Option<Vec<UiInnerInstructions>> = meta.inner_instructions.clone().into();
+            let instructions: Vec<&UiInstruction> = top_level
+                .iter()
+                .chain(inner.iter().flatten().flat_map(|i| &i.instructions))
+                .collect();
+
+            if is_fee_payment(
+                &accounts,
+                &instructions,
+                &meta.pre_balances,
+                &meta.post_balances,
+                &user_pubkey.to_string(),
+                &airdrop_wallet.to_string(),
+                required_lamports,
+            ) {
+                FEE_SCAN_CHECKPOINTS.lock().unwrap().remove(&checkpoint_key);
+                return Ok(Some(sig.to_string()));
             }
+            checked_from.get_or_insert(sig);
+        }
+
+        if last_page || before.is_none() {
+            break;
         }
     }
 
+    if let Some(sig) = checked_from {
+        let mut checkpoints = FEE_SCAN_CHECKPOINTS.lock().unwrap();
+        if checkpoints.len() >= MAX_FEE_SCAN_CHECKPOINTS {
+            checkpoints.clear();
+        }
+        checkpoints.insert(checkpoint_key, sig);
+    }
+
     Ok(None)
 }
-pub async fn send_tokens(to_wallet: &str, token_amount: i32) -> Result<String, AppError> {
-    let rpc_url = env::var("SOLANA_RPC_URL")
-        .map_err(|_| AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Missing SOLANA_RPC_URL"))?;
 
+// Whether the RPC node answers at all, for readiness checks. Blocking, with a
+// short timeout so a hung node fails the probe instead of stalling it.
+pub fn ping_rpc() -> Result<(), AppError> {
+    let rpc_url = env::var("SOLANA_RPC_URL").map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Missing SOLANA_RPC_URL")
+            .with_code(ErrorCode::ConfigError)
+    })?;
+    let rpc = RpcClient::new_with_timeout(rpc_url, std::time::Duration::from_secs(3));
+    rpc.get_latest_blockhash().map_err(|e| {
+        AppError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("RPC unreachable: {}", e),
+        )
+        .with_code(ErrorCode::RpcError)
+    })?;
+    Ok(())
+}
+
+// Create the wallet's associated token account for `mint` if it doesn't
+// exist yet, paid by the fee payer (FEE_PAYER_WALLET_PATH, else the airdrop
+// wallet). Returns whether an account was created. Idempotent, so racing a
+// claim that creates the same account is harmless. Blocking, for at most
+// TRANSFER_CONFIRM_TIMEOUT_SECS of confirmation polling; callers bound it with
+// RPC_PERMITS.
+pub fn ensure_ata(wallet: &str, mint: &str) -> Result<bool, AppError> {
+    check_mint_allowed(mint)?;
+
+    let rpc_url = env::var("SOLANA_RPC_URL").map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Missing SOLANA_RPC_URL")
+            .with_code(ErrorCode::ConfigError)
+    })?;
     let rpc = RpcClient::new(rpc_url);
 
-    let payer = read_keypair_file(env::var("AIR_DROP_WALLET_PATH").map_err(|_| {
+    let owner = Pubkey::from_str(wallet).map_err(|_| {
+        AppError::new(StatusCode::BAD_REQUEST, "Invalid recipient wallet")
+            .with_code(ErrorCode::InvalidWallet)
+    })?;
+    let mint = Pubkey::from_str(mint).map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Invalid mint address")
+            .with_code(ErrorCode::ConfigError)
+    })?;
+
+    let ata = get_associated_token_address(&owner, &mint);
+    if with_retry("get_account", || rpc.get_account(&ata)).is_ok() {
+        return Ok(false);
+    }
+
+    let fee_payer = match load_fee_payer()? {
+        Some(fee_payer) => fee_payer,
+        None => load_payer()?,
+    };
+
+    let ix = spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+        &fee_payer.pubkey(),
+        &owner,
+        &mint,
+        &TOKEN_PROGRAM_ID,
+    );
+    let (blockhash, last_valid_block_height) = with_retry("get_latest_blockhash", || {
+        rpc.get_latest_blockhash_with_commitment(rpc.commitment())
+    })
+    .map_err(|_| {
         AppError::new(
             StatusCode::INTERNAL_SERVER_ERROR,
-            "Missing AIR_DROP_WALLET_PATH",
+            "Failed to fetch blockhash for ATA creation",
         )
-    })?)
-    .map_err(|_| {
+        .with_code(ErrorCode::RpcError)
+    })?;
+    let tx = Transaction::new_signed_with_payer(
+        &with_compute_budget(vec![ix]),
+        Some(&fee_payer.pubkey()),
+        &[&fee_payer],
+        blockhash,
+    );
+
+    // Resending the same signed tx can't create the account twice
+    let failed = |reason: String| {
         AppError::new(
             StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to load wallet keypair",
+            format!("Failed to create ATA: {}", reason),
         )
+        .with_code(ErrorCode::TransferFailed)
+    };
+    let sig = with_retry("send_transaction", || rpc.send_transaction(&tx))
+        .map_err(|e| failed(e.to_string()))?;
+
+    // Left in the queue on any of these; the worker tries again later
+    match await_signature(&rpc, &sig, last_valid_block_height) {
+        SignatureState::Landed(_) => {}
+        SignatureState::InFlight => return Err(failed(format!("{} not confirmed yet", sig))),
+        SignatureState::Dropped => return Err(failed(format!("{} expired unconfirmed", sig))),
+        SignatureState::Failed(err) => return Err(failed(format!("{:?}", err))),
+    }
+
+    tracing::info!(%wallet, tx = %sig, "Pre-created ATA");
+    Ok(true)
+}
+
+// Guard against a bad TOKEN_MINT or campaign sending the wrong token: with
+// ALLOWED_MINTS set, nothing outside it is ever transferred
+pub fn check_mint_allowed(mint: &str) -> Result<(), AppError> {
+    match &CONFIG.allowed_mints {
+        Some(allowed) if !allowed.iter().any(|m| m == mint) => {
+            tracing::error!(%mint, "Refusing to send a mint not in ALLOWED_MINTS");
+            Err(AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Mint {} is not in ALLOWED_MINTS", mint),
+            )
+            .with_code(ErrorCode::MintNotAllowed))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Send `ui_amount` tokens of `mint` and wait, up to
+// TRANSFER_CONFIRM_TIMEOUT_SECS, for the transfer to confirm. A transfer that
+// is still in flight then comes back as TransferStatus::Pending rather than
+// an error: it may yet land, so the caller must not treat it as failed.
+pub async fn send_tokens(
+    to_wallet: &str,
+    mint: &str,
+    ui_amount: u64,
+) -> Result<Transfer, AppError> {
+    check_mint_allowed(mint)?;
+    let _permit = RPC_PERMITS.acquire().await.map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "RPC limiter closed")
+            .with_code(ErrorCode::InternalError)
     })?;
+    // Measured after the permit so queueing behind RPC_MAX_CONCURRENCY isn't
+    // counted as RPC latency
+    let _timer = telemetry::RpcTimer::start("send_tokens");
+
+    let (to_wallet, mint) = (to_wallet.to_string(), mint.to_string());
+    blocking(move || transfer_tokens(&to_wallet, &mint, ui_amount)).await
+}
+
+fn transfer_tokens(to_wallet: &str, mint: &str, ui_amount: u64) -> Result<Transfer, AppError> {
+    let rpc_url = env::var("SOLANA_RPC_URL").map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Missing SOLANA_RPC_URL")
+            .with_code(ErrorCode::ConfigError)
+    })?;
+
+    let rpc = RpcClient::new(rpc_url);
+
+    let payer = load_payer()?;
 
     let payer_pubkey = payer.pubkey();
     println!("🔑 Airdrop wallet: {}", payer_pubkey);
 
-    let mint = Pubkey::from_str(
-        &env::var("TOKEN_MINT")
-            .map_err(|_| AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Missing TOKEN_MINT"))?,
-    )
-    .map_err(|_| AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Invalid mint address"))?;
+    // Optional separate keypair paying tx fees and ATA rent, so the treasury
+    // only needs to hold tokens; the treasury stays the token authority
+    let fee_payer = load_fee_payer()?;
+    let fee_payer_key = fee_payer.as_ref().unwrap_or(&payer);
+    let fee_payer_pubkey = fee_payer_key.pubkey();
+    if fee_payer.is_some() {
+        tracing::debug!(fee_payer = %fee_payer_pubkey, "Using separate fee payer");
+    }
+
+    // The transfer needs the treasury's signature as authority and the fee
+    // payer's as payer; one signature when they're the same key
+    let transfer_signers: Vec<&Keypair> = match &fee_payer {
+        Some(fee_payer) => vec![fee_payer, &payer],
+        None => vec![&payer],
+    };
+
+    let mint = Pubkey::from_str(mint).map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Invalid mint address")
+            .with_code(ErrorCode::ConfigError)
+    })?;
 
-    let to_pubkey = Pubkey::from_str(to_wallet)
-        .map_err(|_| AppError::new(StatusCode::BAD_REQUEST, "Invalid recipient wallet"))?;
+    let to_pubkey = Pubkey::from_str(to_wallet).map_err(|_| {
+        AppError::new(StatusCode::BAD_REQUEST, "Invalid recipient wallet")
+            .with_code(ErrorCode::InvalidWallet)
+    })?;
 
     let payer_token_account = get_associated_token_address(&payer_pubkey, &mint);
     let recipient_token_account = get_associated_token_address(&to_pubkey, &mint);
 
+    let decimals = mint_decimals(&rpc, &mint)?;
+    let amount = ui_to_raw(ui_amount, decimals).ok_or_else(|| {
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Airdrop amount overflows the token's base units",
+        )
+        .with_code(ErrorCode::ConfigError)
+    })?;
+
+    // Bail out before spending anything on ATA creation if the treasury can't
+    // cover the transfer; the caller leaves the fee and points untouched
+    let treasury_balance = token_account_balance(&rpc, &payer_token_account, &mint)?;
+    if treasury_balance.amount < amount {
+        // Warn-level so alerting picks it up: the treasury needs topping up
+        tracing::warn!(
+            %mint,
+            available = %treasury_balance.ui_amount,
+            needed = ui_amount,
+            wallet = to_wallet,
+            "🪫 Treasury out of tokens"
+        );
+        return Err(AppError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Treasury out of tokens, try again later",
+        )
+        .with_code(ErrorCode::TreasuryDepleted));
+    }
+
     // Check if ATA exists
-    match rpc.get_account(&recipient_token_account) {
+    match with_retry("get_account", || rpc.get_account(&recipient_token_account)) {
         Ok(ata_account) => {
             if ata_account.owner != TOKEN_PROGRAM_ID {
                 return Err(AppError::new(
                     StatusCode::BAD_REQUEST,
                     "❌ ATA exists but owned by wrong program",
-                ));
+                )
+                .with_code(ErrorCode::TransferFailed));
             }
             println!("✅ ATA already exists for {}", to_wallet);
         }
@@ -143,47 +1135,35 @@ pub async fn send_tokens(to_wallet: &str, token_amount: i32) -> Result<String, A
 
             let create_ata_ix =
                 spl_associated_token_account::instruction::create_associated_token_account(
-                    &payer_pubkey,
+                    &fee_payer_pubkey,
                     &to_pubkey,
                     &mint,
                     &TOKEN_PROGRAM_ID,
                 );
 
-            let blockhash = rpc.get_latest_blockhash().map_err(|_| {
-                AppError::new(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Failed to fetch blockhash for ATA creation",
-                )
-            })?;
+            let blockhash = with_retry("get_latest_blockhash", || rpc.get_latest_blockhash())
+                .map_err(|_| {
+                    AppError::new(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Failed to fetch blockhash for ATA creation",
+                    )
+                    .with_code(ErrorCode::RpcError)
+                })?;
 
             let ata_tx = Transaction::new_signed_with_payer(
-                &[create_ata_ix],
-                Some(&payer_pubkey),
-                &[&payer],
+                &with_compute_budget(vec![create_ata_ix]),
+                Some(&fee_payer_pubkey),
+                &[fee_payer_key],
                 blockhash,
             );
 
             // 🔍 Simulate ATA creation
-            match rpc.simulate_transaction(&ata_tx) {
-                Ok(sim) => {
-                    if let Some(err) = sim.value.err {
-                        println!("🧨 ATA simulation error: {:?}", err);
-                        if let Some(logs) = sim.value.logs {
-                            for log in logs {
-                                println!("🪵 {}", log);
-                            }
-                        }
-                        return Err(AppError::new(
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            format!("ATA creation simulation failed: {:?}", err),
-                        ));
-                    } else {
-                        println!("🧪 ATA simulation passed ✅");
-                    }
-                }
-                Err(e) => {
-                    println!("❌ Simulation RPC failed: {}", e);
-                }
+            if let Err((err, _)) = simulate(&rpc, &ata_tx, "ATA creation") {
+                return Err(AppError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("ATA creation simulation failed: {:?}", err),
+                )
+                .with_code(ErrorCode::TransferFailed));
             }
 
             rpc.send_and_confirm_transaction(&ata_tx).map_err(|e| {
@@ -191,6 +1171,7 @@ pub async fn send_tokens(to_wallet: &str, token_amount: i32) -> Result<String, A
                     StatusCode::INTERNAL_SERVER_ERROR,
                     format!("❌ Failed to create ATA: {}", e),
                 )
+                .with_code(ErrorCode::TransferFailed)
             })?;
 
             println!("✅ ATA created successfully for {}", to_wallet);
@@ -198,7 +1179,6 @@ pub async fn send_tokens(to_wallet: &str, token_amount: i32) -> Result<String, A
     }
 
     // Token transfer
-    let amount = (token_amount as u64) * 10u64.pow(TOKEN_DECIMALS as u32);
 
     let transfer_ix: Instruction = transfer_checked(
         &TOKEN_PROGRAM_ID,
@@ -208,39 +1188,685 @@ pub async fn send_tokens(to_wallet: &str, token_amount: i32) -> Result<String, A
         &payer_pubkey,
         &[],
         amount,
-        TOKEN_DECIMALS,
+        decimals,
     )
     .map_err(|_| {
         AppError::new(
             StatusCode::INTERNAL_SERVER_ERROR,
             "Failed to build transfer instruction",
         )
+        .with_code(ErrorCode::TransferFailed)
     })?;
+    let transfer_ixs = with_compute_budget(vec![transfer_ix]);
 
-    let blockhash = rpc.get_latest_blockhash().map_err(|_| {
-        AppError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to fetch blockhash for transfer",
-        )
-    })?;
+    // Rebuild and resend with a fresh blockhash if the previous one expired
+    // before the transfer landed. The fee has already been verified by the
+    // caller, so only the build-sign-send step is repeated.
+    let max_attempts = CONFIG.blockhash_retry_limit + 1;
+    let mut attempt = 0;
+    let transfer = loop {
+        attempt += 1;
 
-    let tx = Transaction::new_signed_with_payer(
-        &[transfer_ix],
-        Some(&payer_pubkey),
-        &[&payer],
-        blockhash,
-    );
+        let (blockhash, last_valid_block_height) = with_retry("get_latest_blockhash", || {
+            rpc.get_latest_blockhash_with_commitment(rpc.commitment())
+        })
+        .map_err(|_| {
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to fetch blockhash for transfer",
+            )
+            .with_code(ErrorCode::RpcError)
+        })?;
 
-    let sig = rpc.send_and_confirm_transaction(&tx).map_err(|e| {
-        AppError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("❌ Transfer failed: {}", e),
-        )
-    })?;
+        let tx = Transaction::new_signed_with_payer(
+            &transfer_ixs,
+            Some(&fee_payer_pubkey),
+            &transfer_signers,
+            blockhash,
+        );
+
+        // 🔍 Simulate the transfer once before the first send, so a doomed
+        // transfer fails fast instead of waiting out a confirmation
+        if CONFIG.simulate_transfers && attempt == 1 {
+            if let Err((err, logs)) = simulate(&rpc, &tx, "Transfer") {
+                if is_frozen_tx_error(&err) {
+                    return Err(recipient_frozen());
+                }
+
+                return Err(AppError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Transfer simulation failed: {:?}\n{}", err, logs.join("\n")),
+                )
+                .with_code(ErrorCode::TransferFailed));
+            }
+        }
+
+        // Sent without waiting; confirmation is polled below with a bound. A
+        // transport error may hide a tx the node did accept, so that is
+        // polled too rather than reported as a failure.
+        let sig = match rpc.send_transaction(&tx) {
+            Ok(sig) => sig,
+            Err(e) if e.get_transaction_error().is_none() && is_retryable(&e) => {
+                tracing::warn!(
+                    wallet = to_wallet,
+                    tx = %tx.signatures[0],
+                    error = %e,
+                    "Transfer send failed, checking whether it landed"
+                );
+                tx.signatures[0]
+            }
+            Err(e) if is_blockhash_expired(&e) && attempt < max_attempts => {
+                tracing::warn!(
+                    wallet = to_wallet,
+                    attempt,
+                    max_attempts,
+                    "Transfer blockhash expired, retrying"
+                );
+                continue;
+            }
+            Err(e) if is_blockhash_expired(&e) => return Err(blockhash_expired(attempt)),
+            Err(e) if is_account_frozen(&e) => {
+                tracing::warn!(wallet = to_wallet, error = %e, "Recipient token account frozen");
+                return Err(recipient_frozen());
+            }
+            Err(e) => {
+                return Err(AppError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("❌ Transfer failed: {}", e),
+                )
+                .with_code(ErrorCode::TransferFailed));
+            }
+        };
+
+        match await_signature(&rpc, &sig, last_valid_block_height) {
+            SignatureState::Landed(status) => {
+                break Transfer {
+                    signature: sig.to_string(),
+                    status,
+                }
+            }
+            // Pending status for the reconciler; see reconcile.rs
+            SignatureState::InFlight => {
+                tracing::warn!(wallet = to_wallet, tx = %sig, "Transfer not confirmed yet");
+                return Ok(Transfer {
+                    signature: sig.to_string(),
+                    status: TransferStatus::Pending {
+                        last_valid_block_height,
+                    },
+                });
+            }
+            // The old tx can no longer land, so a fresh one can't double-send
+            SignatureState::Dropped if attempt < max_attempts => {
+                tracing::warn!(
+                    wallet = to_wallet,
+                    tx = %sig,
+                    attempt,
+                    max_attempts,
+                    "Transfer dropped with its blockhash, retrying"
+                );
+            }
+            SignatureState::Dropped => return Err(blockhash_expired(attempt)),
+            SignatureState::Failed(err) if is_frozen_tx_error(&err) => {
+                tracing::warn!(wallet = to_wallet, tx = %sig, error = ?err, "Recipient token account frozen");
+                return Err(recipient_frozen());
+            }
+            SignatureState::Failed(err) => {
+                return Err(AppError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("❌ Transfer failed: {:?}", err),
+                )
+                .with_code(ErrorCode::TransferFailed));
+            }
+        }
+    };
 
     println!("✅ Tokens sent: {} to {}", amount, to_wallet);
-    println!("🔗 Tx: https://solscan.io/tx/{}", sig);
+    println!("🔗 Tx: https://solscan.io/tx/{}", transfer.signature);
+
+    Ok(transfer)
+}
+
+// Dry-run `tx` and log the outcome; `what` names it in the logs. Err carries
+// the error and program logs it would fail with. If the simulation itself
+// can't be run that is only logged, and sending reports any real problem.
+fn simulate(
+    rpc: &RpcClient,
+    tx: &Transaction,
+    what: &str,
+) -> Result<(), (TransactionError, Vec<String>)> {
+    match rpc.simulate_transaction(tx) {
+        Ok(sim) => match sim.value.err {
+            Some(err) => {
+                let logs = sim.value.logs.unwrap_or_default();
+                tracing::warn!(what, error = ?err, logs = ?logs, "Simulation failed");
+                Err((err, logs))
+            }
+            None => {
+                tracing::debug!(what, "Simulation passed");
+                Ok(())
+            }
+        },
+        Err(e) => {
+            tracing::warn!(what, error = %e, "Simulation RPC failed");
+            Ok(())
+        }
+    }
+}
+
+fn blockhash_expired(attempts: u32) -> AppError {
+    AppError::new(
+        StatusCode::SERVICE_UNAVAILABLE,
+        format!(
+            "❌ Transfer failed: blockhash expired after {} attempts",
+            attempts
+        ),
+    )
+    .with_code(ErrorCode::BlockhashExpired)
+}
+
+fn recipient_frozen() -> AppError {
+    AppError::new(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "Recipient token account frozen; ask the token issuer to thaw it, then claim again",
+    )
+    .with_code(ErrorCode::RecipientFrozen)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::block_on;
+    use solana_sdk::{
+        address_lookup_table::AddressLookupTableAccount,
+        hash::Hash,
+        message::{v0, v0::LoadedAddresses, VersionedMessage},
+        system_instruction,
+        transaction::VersionedTransaction,
+    };
+    use solana_transaction_status::{
+        ConfirmedTransactionWithStatusMeta, TransactionStatusMeta, TransactionWithStatusMeta,
+        VersionedTransactionWithStatusMeta,
+    };
+    use std::cell::Cell;
+
+    const START_BALANCE: u64 = 1_000_000_000;
+    const TX_FEE: u64 = 5_000;
+
+    // One entry of the airdrop wallet's signature history
+    struct Entry {
+        signature: String,
+        slot: u64,
+        block_time: Option<i64>,
+        tx: Option<ConfirmedTransactionWithStatusMeta>,
+        encoding: UiTransactionEncoding,
+    }
+
+    // A canned history, newest first, served the way the node pages it
+    #[derive(Default)]
+    struct FakeRpc {
+        slot: u64,
+        history: Vec<Entry>,
+        fetched: Cell<usize>,
+    }
+
+    impl FakeRpc {
+        fn push_newest(&mut self, sig: Signature, tx: ConfirmedTransactionWithStatusMeta) {
+            self.push_newest_as(sig, tx, UiTransactionEncoding::JsonParsed);
+        }
+
+        fn push_newest_as(
+            &mut self,
+            sig: Signature,
+            tx: ConfirmedTransactionWithStatusMeta,
+            encoding: UiTransactionEncoding,
+        ) {
+            self.history.insert(
+                0,
+                Entry {
+                    signature: sig.to_string(),
+                    slot: tx.slot,
+                    block_time: tx.block_time,
+                    tx: Some(tx),
+                    encoding,
+                },
+            );
+        }
+    }
+
+    impl FeeScanRpc for FakeRpc {
+        fn slot(&self) -> Result<u64, ClientError> {
+            Ok(self.slot)
+        }
+
+        fn signatures(
+            &self,
+            _address: &Pubkey,
+            config: GetConfirmedSignaturesForAddress2Config,
+        ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>, ClientError> {
+            let start = match config.before {
+                Some(before) => self
+                    .history
+                    .iter()
+                    .position(|e| e.signature == before.to_string())
+                    .map_or(self.history.len(), |i| i + 1),
+                None => 0,
+            };
+            let until = config.until.map(|u| u.to_string());
+            Ok(self.history[start..]
+                .iter()
+                .take_while(|e| Some(&e.signature) != until.as_ref())
+                .take(config.limit.unwrap_or(1_000))
+                .map(|e| RpcConfirmedTransactionStatusWithSignature {
+                    signature: e.signature.clone(),
+                    slot: e.slot,
+                    err: None,
+                    memo: None,
+                    block_time: e.block_time,
+                    confirmation_status: None,
+                })
+                .collect())
+        }
+
+        fn transaction(
+            &self,
+            sig: &Signature,
+        ) -> Result<EncodedConfirmedTransactionWithStatusMeta, ClientError> {
+            self.fetched.set(self.fetched.get() + 1);
+            self.history
+                .iter()
+                .find(|e| e.signature == sig.to_string())
+                .and_then(|e| Some(e.tx.clone()?.encode(e.encoding, Some(0)).unwrap()))
+                .ok_or_else(|| ClientErrorKind::Custom("not found".to_string()).into())
+        }
+    }
+
+    // A transaction as the node returns it, with the given balances (in
+    // account order) before and after
+    fn confirmed(
+        tx: VersionedTransaction,
+        pre_balances: Vec<u64>,
+        post_balances: Vec<u64>,
+        meta: TransactionStatusMeta,
+    ) -> ConfirmedTransactionWithStatusMeta {
+        ConfirmedTransactionWithStatusMeta {
+            slot: 100,
+            tx_with_meta: TransactionWithStatusMeta::Complete(VersionedTransactionWithStatusMeta {
+                transaction: tx,
+                meta: TransactionStatusMeta {
+                    fee: TX_FEE,
+                    pre_balances,
+                    post_balances,
+                    ..meta
+                },
+            }),
+            block_time: Some(Utc::now().timestamp()),
+        }
+    }
+
+    // `from` sending `to` `lamports` with a plain system transfer
+    fn transfer(
+        from: &Keypair,
+        to: &Pubkey,
+        lamports: u64,
+    ) -> (Signature, ConfirmedTransactionWithStatusMeta) {
+        send(
+            from,
+            system_instruction::transfer(&from.pubkey(), to, lamports),
+            lamports,
+        )
+    }
+
+    // `from` signing and paying for `ix`, which moves `lamports` from it to
+    // the instruction's second account
+    fn send(
+        from: &Keypair,
+        ix: Instruction,
+        lamports: u64,
+    ) -> (Signature, ConfirmedTransactionWithStatusMeta) {
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&from.pubkey()),
+            &[from],
+            Hash::default(),
+        );
+        // Accounts: payer, recipient, program
+        let pre = vec![START_BALANCE, 0, 1];
+        let post = vec![START_BALANCE - lamports - TX_FEE, lamports, 1];
+        (
+            tx.signatures[0],
+            confirmed(tx.into(), pre, post, TransactionStatusMeta::default()),
+        )
+    }
+
+    fn scan(rpc: &FakeRpc, user: &Keypair, airdrop: &Pubkey, required: u64) -> Option<String> {
+        find_fee_payment(rpc, &user.pubkey(), airdrop, required).unwrap()
+    }
+
+    #[test]
+    fn the_paying_transactions_signature_is_returned() {
+        let (user, airdrop) = (Keypair::new(), Pubkey::new_unique());
+        let mut rpc = FakeRpc::default();
+        let (paid, tx) = transfer(&user, &airdrop, 10_000);
+        rpc.push_newest(paid, tx);
+        // A newer attempt that failed on chain, and other wallets' payments
+        let (failed, mut tx) = transfer(&user, &airdrop, 20_000);
+        if let TransactionWithStatusMeta::Complete(with_meta) = &mut tx.tx_with_meta {
+            with_meta.meta.status = Err(TransactionError::InsufficientFundsForFee);
+        }
+        rpc.push_newest(failed, tx);
+        push_others(&mut rpc, &airdrop, 3);
+
+        assert_eq!(scan(&rpc, &user, &airdrop, 10_000), Some(paid.to_string()));
+    }
+
+    // `count` newer payments to the airdrop wallet from other wallets
+    fn push_others(rpc: &mut FakeRpc, airdrop: &Pubkey, count: usize) {
+        for _ in 0..count {
+            let (sig, tx) = transfer(&Keypair::new(), airdrop, 10_000);
+            rpc.push_newest(sig, tx);
+        }
+    }
+
+    #[test]
+    fn a_payment_past_the_first_page_is_found() {
+        let (user, airdrop) = (Keypair::new(), Pubkey::new_unique());
+        let mut rpc = FakeRpc::default();
+        let (sig, tx) = transfer(&user, &airdrop, 10_000);
+        rpc.push_newest(sig, tx);
+        push_others(&mut rpc, &airdrop, FEE_SCAN_PAGE_SIZE + 50);
+
+        assert_eq!(scan(&rpc, &user, &airdrop, 10_000), Some(sig.to_string()));
+        assert_eq!(rpc.fetched.get(), FEE_SCAN_PAGE_SIZE + 51);
+    }
+
+    #[test]
+    fn a_rescan_stops_at_the_checkpoint() {
+        let (user, airdrop) = (Keypair::new(), Pubkey::new_unique());
+        let mut rpc = FakeRpc::default();
+        push_others(&mut rpc, &airdrop, 5);
+        assert_eq!(scan(&rpc, &user, &airdrop, 10_000), None);
+        assert_eq!(rpc.fetched.get(), 5);
+
+        // Only what arrived since the last scan is fetched
+        push_others(&mut rpc, &airdrop, 2);
+        assert_eq!(scan(&rpc, &user, &airdrop, 10_000), None);
+        assert_eq!(rpc.fetched.get(), 7);
+
+        let (sig, tx) = transfer(&user, &airdrop, 10_000);
+        rpc.push_newest(sig, tx);
+        assert_eq!(scan(&rpc, &user, &airdrop, 10_000), Some(sig.to_string()));
+        assert_eq!(rpc.fetched.get(), 8);
+    }
+
+    #[test]
+    fn a_v0_payment_to_a_lookup_table_address_is_found() {
+        for encoding in [
+            UiTransactionEncoding::Json,
+            UiTransactionEncoding::JsonParsed,
+        ] {
+            let (user, airdrop) = (Keypair::new(), Pubkey::new_unique());
+            let table = AddressLookupTableAccount {
+                key: Pubkey::new_unique(),
+                addresses: vec![airdrop],
+            };
+            let message = v0::Message::try_compile(
+                &user.pubkey(),
+                &[system_instruction::transfer(
+                    &user.pubkey(),
+                    &airdrop,
+                    10_000,
+                )],
+                &[table],
+                Hash::default(),
+            )
+            .unwrap();
+            let tx =
+                VersionedTransaction::try_new(VersionedMessage::V0(message), &[&user]).unwrap();
+            // Accounts: payer, system program, then the airdrop wallet loaded
+            // from the table
+            let pre = vec![START_BALANCE, 1, 0];
+            let post = vec![START_BALANCE - 10_000 - TX_FEE, 1, 10_000];
+            let meta = TransactionStatusMeta {
+                loaded_addresses: LoadedAddresses {
+                    writable: vec![airdrop],
+                    readonly: vec![],
+                },
+                ..TransactionStatusMeta::default()
+            };
+            let sig = tx.signatures[0];
+            let mut rpc = FakeRpc::default();
+            rpc.push_newest_as(sig, confirmed(tx, pre, post, meta), encoding);
+
+            assert_eq!(
+                scan(&rpc, &user, &airdrop, 10_000),
+                Some(sig.to_string()),
+                "{encoding:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn a_transfer_to_another_wallet_is_not_a_fee() {
+        let (user, airdrop) = (Keypair::new(), Pubkey::new_unique());
+        let mut rpc = FakeRpc::default();
+        let (sig, tx) = transfer(&user, &Pubkey::new_unique(), 10_000);
+        rpc.push_newest(sig, tx);
+
+        assert_eq!(scan(&rpc, &user, &airdrop, 10_000), None);
+    }
+
+    #[test]
+    fn an_underpayment_is_not_a_fee() {
+        let (user, airdrop) = (Keypair::new(), Pubkey::new_unique());
+        let mut rpc = FakeRpc::default();
+        let (sig, tx) = transfer(&user, &airdrop, 9_999);
+        rpc.push_newest(sig, tx);
+
+        assert_eq!(scan(&rpc, &user, &airdrop, 10_000), None);
+        assert_eq!(scan(&rpc, &user, &airdrop, 9_999), Some(sig.to_string()));
+    }
+
+    #[test]
+    fn a_transfer_through_another_program_is_not_a_fee() {
+        let (user, airdrop) = (Keypair::new(), Pubkey::new_unique());
+        let mut rpc = FakeRpc::default();
+        // Same accounts, data and balance changes as a system transfer
+        let system = system_instruction::transfer(&user.pubkey(), &airdrop, 10_000);
+        let lookalike = Instruction {
+            program_id: Pubkey::new_unique(),
+            ..system
+        };
+        let (sig, tx) = send(&user, lookalike, 10_000);
+        rpc.push_newest(sig, tx);
+
+        assert_eq!(scan(&rpc, &user, &airdrop, 10_000), None);
+    }
+
+    #[test]
+    fn a_self_transfer_is_not_a_fee() {
+        let user = Keypair::new();
+        let mut rpc = FakeRpc::default();
+        let tx = Transaction::new_signed_with_payer(
+            &[system_instruction::transfer(
+                &user.pubkey(),
+                &user.pubkey(),
+                10_000,
+            )],
+            Some(&user.pubkey()),
+            &[&user],
+            Hash::default(),
+        );
+        // Accounts: the wallet, system program
+        let (pre, post) = (vec![START_BALANCE, 1], vec![START_BALANCE - TX_FEE, 1]);
+        let sig = tx.signatures[0];
+        rpc.push_newest(
+            sig,
+            confirmed(tx.into(), pre, post, TransactionStatusMeta::default()),
+        );
+
+        assert_eq!(scan(&rpc, &user, &user.pubkey(), 10_000), None);
+    }
 
-    Ok(sig.to_string())
+    #[test]
+    fn a_payment_the_airdrop_wallet_signed_is_not_a_fee() {
+        let (user, airdrop) = (Keypair::new(), Keypair::new());
+        let mut rpc = FakeRpc::default();
+        // The airdrop wallet pays the tx fee, so it nets the transfer less that
+        let tx = Transaction::new_signed_with_payer(
+            &[system_instruction::transfer(
+                &user.pubkey(),
+                &airdrop.pubkey(),
+                10_000,
+            )],
+            Some(&airdrop.pubkey()),
+            &[&airdrop, &user],
+            Hash::default(),
+        );
+        // Accounts: airdrop wallet (payer), user, system program
+        let pre = vec![START_BALANCE, START_BALANCE, 1];
+        let post = vec![START_BALANCE + 10_000 - TX_FEE, START_BALANCE - 10_000, 1];
+        let sig = tx.signatures[0];
+        rpc.push_newest(
+            sig,
+            confirmed(tx.into(), pre, post, TransactionStatusMeta::default()),
+        );
 
+        assert_eq!(scan(&rpc, &user, &airdrop.pubkey(), 10_000 - TX_FEE), None);
+    }
+
+    #[test]
+    fn a_malformed_signature_is_skipped_not_fatal() {
+        let (user, airdrop) = (Keypair::new(), Pubkey::new_unique());
+        let mut rpc = FakeRpc::default();
+        let (sig, tx) = transfer(&user, &airdrop, 10_000);
+        rpc.push_newest(sig, tx);
+        rpc.history.insert(
+            0,
+            Entry {
+                signature: "not base58: 0OIl".to_string(),
+                slot: 101,
+                block_time: Some(Utc::now().timestamp()),
+                tx: None,
+                encoding: UiTransactionEncoding::JsonParsed,
+            },
+        );
+
+        assert_eq!(scan(&rpc, &user, &airdrop, 10_000), Some(sig.to_string()));
+    }
+
+    #[test]
+    fn a_malformed_account_key_never_matches() {
+        let (user, airdrop) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let accounts = vec![
+            ("not base58: 0OIl".to_string(), true),
+            (airdrop.to_string(), false),
+        ];
+        let ix = UiInstruction::Compiled(solana_transaction_status::UiCompiledInstruction {
+            program_id_index: 0,
+            accounts: vec![0, 1],
+            data: "0OIl".to_string(),
+            stack_height: None,
+        });
+
+        assert!(!is_fee_payment(
+            &accounts,
+            &[&ix],
+            &[START_BALANCE, 0],
+            &[0, START_BALANCE],
+            &user.to_string(),
+            &airdrop.to_string(),
+            1,
+        ));
+        assert_eq!(system_transfer(&ix, &accounts), None);
+    }
+
+    #[test]
+    fn a_valid_wallet_parses_to_its_key() {
+        let key = Keypair::new().pubkey();
+        assert_eq!(validate_wallet(&key.to_string()).unwrap(), key);
+    }
+
+    #[test]
+    fn malformed_wallets_are_a_bad_request() {
+        let key = Keypair::new().pubkey().to_string();
+        for wallet in [
+            "",
+            // 0, O, I and l aren't in the base58 alphabet
+            "0OIl0OIl0OIl0OIl0OIl0OIl0OIl0OIl",
+            // Valid base58, but too short and too long for a 32-byte key
+            &key[..key.len() - 4],
+            &format!("{key}1111"),
+        ] {
+            let err = validate_wallet(wallet).unwrap_err();
+            assert_eq!(err.status, StatusCode::BAD_REQUEST, "{wallet:?}");
+            assert_eq!(err.code, Some(ErrorCode::InvalidWallet), "{wallet:?}");
+        }
+    }
+
+    #[test]
+    fn token_amounts_scale_by_the_mints_decimals() {
+        assert_eq!(ui_to_raw(1_000, 6), Some(1_000_000_000));
+        assert_eq!(ui_to_raw(1_000, 9), Some(1_000_000_000_000));
+        assert_eq!(ui_to_raw(1_000, 0), Some(1_000));
+        // u64::MAX is about 1.8e19, so this fits at 9 decimals but not at 10
+        assert_eq!(
+            ui_to_raw(18_000_000_000, 9),
+            Some(18_000_000_000_000_000_000)
+        );
+        assert_eq!(ui_to_raw(18_000_000_000, 10), None);
+        assert_eq!(ui_to_raw(1, 20), None);
+    }
+
+    #[test]
+    fn a_payment_exactly_max_age_old_still_counts() {
+        let now = Utc::now().timestamp();
+        assert!(!too_old(now, now, 600));
+        assert!(!too_old(now, now - 600, 600));
+        assert!(too_old(now, now - 601, 600));
+    }
+
+    #[test]
+    fn a_payment_needs_exactly_min_confirmations() {
+        assert!(has_confirmations(132, 100, 32));
+        assert!(!has_confirmations(131, 100, 32));
+        assert!(has_confirmations(100, 100, 0));
+        // A node behind the one that served the signature reports an older slot
+        assert!(!has_confirmations(99, 100, 1));
+    }
+
+    #[test]
+    fn a_login_challenge_signed_by_another_key_is_refused() {
+        block_on(async {
+            let owner = Keypair::new();
+            let wallet = owner.pubkey().to_string();
+            let challenge = auth::issue_nonce(&wallet).await.unwrap();
+
+            let forged = Keypair::new().sign_message(challenge.message.as_bytes());
+            let err = verify_signed_message(&wallet, &challenge.nonce, &forged.to_string())
+                .await
+                .unwrap_err();
+            assert_eq!(err.status, StatusCode::UNAUTHORIZED);
+            assert_eq!(err.code, Some(ErrorCode::InvalidSignature));
+
+            // A failed attempt doesn't burn the nonce for the real owner
+            let signed = owner.sign_message(challenge.message.as_bytes());
+            verify_signed_message(&wallet, &challenge.nonce, &signed.to_string())
+                .await
+                .unwrap();
+        });
+    }
+
+    #[test]
+    fn a_malformed_login_signature_is_refused() {
+        block_on(async {
+            let wallet = Keypair::new().pubkey().to_string();
+            let challenge = auth::issue_nonce(&wallet).await.unwrap();
+
+            let err = verify_signed_message(&wallet, &challenge.nonce, "not-a-signature")
+                .await
+                .unwrap_err();
+            assert_eq!(err.status, StatusCode::BAD_REQUEST);
+            assert_eq!(err.code, Some(ErrorCode::InvalidSignature));
+        });
+    }
 }
@@ -0,0 +1,156 @@
+use crate::error::AppError;
+use axum::{
+    extract::MatchedPath,
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use once_cell::sync::OnceCell;
+use std::time::{Duration, Instant};
+use tracing::Instrument;
+use uuid::Uuid;
+
+static PROMETHEUS: OnceCell<PrometheusHandle> = OnceCell::new();
+
+static X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+tokio::task_local! {
+    static REQUEST_ID: Uuid;
+}
+
+// Id of the request being handled, if called from within one (spawned
+// background tasks don't inherit it)
+pub fn current_request_id() -> Option<Uuid> {
+    REQUEST_ID.try_with(|id| *id).ok()
+}
+
+// Give every request an id, reusing a valid `x-request-id` set by a proxy.
+// Everything logged while handling it is inside a span carrying the id, the
+// id is echoed back in `x-request-id` and in error bodies, and one line with
+// method, path, status and latency is logged when it completes.
+pub async fn request_context(req: Request, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(&X_REQUEST_ID)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| Uuid::parse_str(v).ok())
+        .unwrap_or_else(Uuid::new_v4);
+    let span = tracing::info_span!(
+        "request",
+        request_id = %id,
+        method = %req.method(),
+        path = %req.uri().path()
+    );
+    let start = Instant::now();
+
+    let mut res = REQUEST_ID
+        .scope(id, next.run(req).instrument(span.clone()))
+        .await;
+
+    span.in_scope(|| {
+        tracing::info!(
+            status = res.status().as_u16(),
+            latency_ms = start.elapsed().as_millis() as u64,
+            "Request completed"
+        )
+    });
+    if let Ok(value) = HeaderValue::from_str(&id.to_string()) {
+        res.headers_mut().insert(X_REQUEST_ID.clone(), value);
+    }
+    res
+}
+
+// Histogram buckets in seconds; RPC calls (send_and_confirm especially) sit
+// at the upper end
+const BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+// Install the global metrics recorder. Must run before anything records, so
+// main calls it before building the router.
+pub fn init() {
+    let handle = PrometheusBuilder::new()
+        .set_buckets(BUCKETS)
+        .expect("Invalid metrics buckets")
+        .install_recorder()
+        .expect("Failed to install metrics recorder");
+
+    // Histograms are only folded into the rendered output during upkeep
+    let upkeep = handle.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(5));
+        loop {
+            interval.tick().await;
+            upkeep.run_upkeep();
+        }
+    });
+
+    let _ = PROMETHEUS.set(handle);
+}
+
+// Everything recorded so far, in the Prometheus text format
+pub fn render() -> String {
+    PROMETHEUS.get().map(|h| h.render()).unwrap_or_default()
+}
+
+// Count and time every request by route template (not raw path, so wallet
+// addresses in paths don't explode the label set) and status
+pub async fn track_requests(req: Request, next: Next) -> Response {
+    let method = req.method().to_string();
+    let path = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let start = Instant::now();
+
+    let res = next.run(req).await;
+
+    let status = res.status().as_u16().to_string();
+    metrics::counter!("http_requests_total", "method" => method.clone(), "path" => path.clone(), "status" => status)
+        .increment(1);
+    metrics::histogram!("http_request_duration_seconds", "method" => method, "path" => path)
+        .record(start.elapsed().as_secs_f64());
+    res
+}
+
+// Records how long a Solana RPC operation took when dropped, so early
+// returns are timed too
+pub struct RpcTimer {
+    call: &'static str,
+    start: Instant,
+}
+
+impl RpcTimer {
+    pub fn start(call: &'static str) -> Self {
+        RpcTimer {
+            call,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for RpcTimer {
+    fn drop(&mut self) {
+        metrics::histogram!("solana_rpc_duration_seconds", "call" => self.call)
+            .record(self.start.elapsed().as_secs_f64());
+    }
+}
+
+pub fn record_airdrop_sent(project: &str, tokens: i64) {
+    metrics::counter!("airdrops_sent_total", "project" => project.to_string()).increment(1);
+    metrics::counter!("airdrop_tokens_sent_total", "project" => project.to_string())
+        .increment(tokens.max(0) as u64);
+}
+
+// Labelled with the error code clients see, e.g. FEE_NOT_DETECTED
+pub fn record_claim_failed(err: &AppError) {
+    let reason = err
+        .code
+        .and_then(|c| serde_json::to_value(c).ok())
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_else(|| "UNKNOWN".to_string());
+    metrics::counter!("claims_failed_total", "reason" => reason).increment(1);
+}
@@ -0,0 +1,56 @@
+// Helpers shared by the unit tests. Database tests run against DATABASE_URL
+// (from .env), with the migrations already applied, and use fresh random
+// wallets so they never collide with each other or with real rows.
+use once_cell::sync::Lazy;
+use solana_sdk::signature::{Keypair, Signer};
+use std::future::Future;
+
+// DB_POOL keeps its connections on the runtime that opened them, so every
+// test that touches the database runs on this one instead of its own
+static RUNTIME: Lazy<tokio::runtime::Runtime> = Lazy::new(|| {
+    dotenvy::dotenv().ok();
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to build test runtime")
+});
+
+pub fn block_on<F: Future>(f: F) -> F::Output {
+    RUNTIME.block_on(f)
+}
+
+// Pubkey::new_unique would restart from the same keys every run and pick up
+// the rows an earlier run left behind
+pub fn random_wallet() -> String {
+    Keypair::new().pubkey().to_string()
+}
+
+// Stand-in for a transaction signature; only has to be unique
+pub fn random_signature() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+// An empty project of its own, using the deployment-wide settings
+pub async fn random_project() -> String {
+    let id = format!("test-{}", uuid::Uuid::new_v4());
+    sqlx::query!("INSERT INTO projects (id, name) VALUES ($1, $1)", id)
+        .execute(&*crate::db::DB_POOL)
+        .await
+        .unwrap();
+    id
+}
+
+// A user in the 'default' project holding `points`
+pub async fn user_with_points(points: i32) -> String {
+    let wallet = random_wallet();
+    let id = crate::db::create_user("default", &wallet).await.unwrap();
+    sqlx::query!(
+        "UPDATE users SET total_points = $1 WHERE id = $2",
+        points,
+        id
+    )
+    .execute(&*crate::db::DB_POOL)
+    .await
+    .unwrap();
+    wallet
+}
@@ -0,0 +1,163 @@
+// with_retry carries solana_client's ClientError, which is large but is what
+// every RpcClient call returns
+#![allow(clippy::result_large_err)]
+
+use crate::{
+    error::{AppError, ErrorCode},
+    models::task::TaskVerification,
+    solana,
+};
+use axum::http::StatusCode;
+use once_cell::sync::Lazy;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use spl_associated_token_account::get_associated_token_address;
+use std::{
+    collections::HashMap,
+    env,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+// Balances are re-read at most this often per wallet/mint, so retrying a
+// task doesn't hammer the RPC node
+const BALANCE_CACHE_TTL: Duration = Duration::from_secs(30);
+
+// (wallet, mint) -> (raw balance, decimals, fetched at)
+type BalanceCache = HashMap<(Pubkey, Pubkey), (u64, u8, Instant)>;
+static BALANCE_CACHE: Lazy<Mutex<BalanceCache>> = Lazy::new(|| Mutex::new(HashMap::new()));
+// When BALANCE_CACHE was last swept of expired entries
+static LAST_PRUNE: Lazy<Mutex<Instant>> = Lazy::new(|| Mutex::new(Instant::now()));
+
+// Check a task's on-chain condition for the wallet. Tasks without a
+// verify_type always pass.
+pub async fn check_task(wallet: &str, verification: &TaskVerification) -> Result<(), AppError> {
+    match verification.verify_type.as_deref() {
+        None => Ok(()),
+        Some("onchain_balance") => check_balance(wallet, verification).await,
+        Some(other) => Err(AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Unknown task verify_type: {}", other),
+        )
+        .with_code(ErrorCode::ConfigError)),
+    }
+}
+
+async fn check_balance(wallet: &str, verification: &TaskVerification) -> Result<(), AppError> {
+    let misconfigured = || {
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Task balance check is missing a valid mint or minimum amount",
+        )
+        .with_code(ErrorCode::ConfigError)
+    };
+
+    let mint = verification
+        .verify_mint
+        .as_deref()
+        .and_then(|m| Pubkey::from_str(m).ok())
+        .ok_or_else(misconfigured)?;
+    let min_amount = verification
+        .verify_min_amount
+        .filter(|a| *a >= 0)
+        .ok_or_else(misconfigured)? as u64;
+
+    let owner = solana::validate_wallet(wallet)?;
+
+    let (balance, decimals) = solana::blocking(move || token_balance(&owner, &mint)).await?;
+    let required = solana::ui_to_raw(min_amount, decimals).unwrap_or(u64::MAX);
+
+    if balance < required {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "On-chain condition not met: holding {} of {}, need at least {}",
+                format_amount(balance, decimals),
+                mint,
+                min_amount
+            ),
+        )
+        .with_code(ErrorCode::OnchainConditionNotMet));
+    }
+
+    Ok(())
+}
+
+// Raw balance of the wallet's associated token account for `mint` (0 if it
+// has none) and the mint's decimals. Only a definite answer is cached, never
+// an RPC failure.
+fn token_balance(owner: &Pubkey, mint: &Pubkey) -> Result<(u64, u8), AppError> {
+    if let Some((balance, decimals, at)) = BALANCE_CACHE.lock().unwrap().get(&(*owner, *mint)) {
+        if at.elapsed() < BALANCE_CACHE_TTL {
+            return Ok((*balance, *decimals));
+        }
+    }
+
+    let rpc_url = env::var("SOLANA_RPC_URL").map_err(|_| {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Missing SOLANA_RPC_URL")
+            .with_code(ErrorCode::ConfigError)
+    })?;
+    let rpc = RpcClient::new(rpc_url);
+
+    let decimals = solana::mint_decimals(&rpc, mint)?;
+    let ata = get_associated_token_address(owner, mint);
+
+    let rpc_error = |_| {
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to fetch token balance",
+        )
+        .with_code(ErrorCode::RpcError)
+    };
+
+    // A missing token account just means the wallet holds none
+    let account = solana::with_retry("get_account", || {
+        rpc.get_account_with_commitment(&ata, rpc.commitment())
+    })
+    .map_err(rpc_error)?
+    .value;
+    let balance = match account {
+        Some(_) => solana::with_retry("get_token_account_balance", || {
+            rpc.get_token_account_balance(&ata)
+        })
+        .map_err(rpc_error)?
+        .amount
+        .parse()
+        .unwrap_or(0),
+        None => 0,
+    };
+
+    let now = Instant::now();
+    prune(now);
+    BALANCE_CACHE
+        .lock()
+        .unwrap()
+        .insert((*owner, *mint), (balance, decimals, now));
+    Ok((balance, decimals))
+}
+
+// At most once a TTL, forget balances that have expired
+fn prune(now: Instant) {
+    let mut last_prune = LAST_PRUNE.lock().unwrap();
+    if now.duration_since(*last_prune) < BALANCE_CACHE_TTL {
+        return;
+    }
+    *last_prune = now;
+    BALANCE_CACHE
+        .lock()
+        .unwrap()
+        .retain(|_, (_, _, at)| now.duration_since(*at) < BALANCE_CACHE_TTL);
+}
+
+// Raw token units as a decimal string, e.g. 1500000 with 6 decimals -> "1.5"
+fn format_amount(raw: u64, decimals: u8) -> String {
+    let scale = 10u64.pow(decimals as u32);
+    let frac = format!("{:0width$}", raw % scale, width = decimals as usize);
+    let frac = frac.trim_end_matches('0');
+    if frac.is_empty() {
+        format!("{}", raw / scale)
+    } else {
+        format!("{}.{}", raw / scale, frac)
+    }
+}
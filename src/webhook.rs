@@ -0,0 +1,161 @@
+use crate::{config::CONFIG, db, events::AirdropEvent};
+use serde_json::json;
+use std::{sync::Mutex, time::Duration};
+use tokio::{
+    sync::mpsc,
+    task::JoinHandle,
+    time::{sleep_until, Instant},
+};
+
+const RECEIPT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Dropping the sender is what tells the worker to flush and stop
+static SENDER: Mutex<Option<mpsc::UnboundedSender<AirdropEvent>>> = Mutex::new(None);
+static WORKER: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+
+// Start the batching worker if WEBHOOK_URL is configured
+pub fn start() {
+    let Some(url) = CONFIG.webhook_url.clone() else {
+        return;
+    };
+    let window = Duration::from_millis(CONFIG.webhook_batch_window_ms);
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    *SENDER.lock().unwrap() = Some(tx);
+    *WORKER.lock().unwrap() = Some(tokio::spawn(run(url, window, rx)));
+}
+
+// Queue an airdrop for the next batch; a no-op when webhooks are disabled
+pub fn enqueue(event: AirdropEvent) {
+    if let Some(tx) = SENDER.lock().unwrap().as_ref() {
+        let _ = tx.send(event);
+    }
+}
+
+// Flush whatever is pending and wait for the worker to exit
+pub async fn shutdown() {
+    SENDER.lock().unwrap().take();
+    let worker = WORKER.lock().unwrap().take();
+    if let Some(worker) = worker {
+        let _ = worker.await;
+    }
+}
+
+// Collects events until `window` has passed since the first one of a batch,
+// then sends them together
+async fn run(url: String, window: Duration, mut rx: mpsc::UnboundedReceiver<AirdropEvent>) {
+    let client = reqwest::Client::new();
+    let mut batch = Vec::new();
+    let mut deadline: Option<Instant> = None;
+
+    loop {
+        tokio::select! {
+            msg = rx.recv() => match msg {
+                Some(event) => {
+                    if batch.is_empty() {
+                        deadline = Some(Instant::now() + window);
+                    }
+                    batch.push(event);
+                }
+                None => {
+                    flush(&client, &url, &mut batch).await;
+                    break;
+                }
+            },
+            _ = sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                flush(&client, &url, &mut batch).await;
+                deadline = None;
+            }
+        }
+    }
+}
+
+async fn flush(client: &reqwest::Client, url: &str, batch: &mut Vec<AirdropEvent>) {
+    if batch.is_empty() {
+        return;
+    }
+
+    let content = batch
+        .iter()
+        .map(|e| format!("🎉 {} claimed {} tokens", e.wallet, e.amount))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let body = json!({ "content": content, "events": batch });
+
+    match client.post(url).json(&body).send().await {
+        Ok(res) if !res.status().is_success() => {
+            tracing::warn!(status = %res.status(), count = batch.len(), "Webhook rejected airdrop batch");
+        }
+        Ok(_) => {}
+        Err(e) => {
+            tracing::warn!(error = %e, count = batch.len(), "Failed to deliver airdrop webhook");
+        }
+    }
+
+    batch.clear();
+}
+
+// Fire-and-forget receipt to an opted-in user after a successful claim.
+// Runs on its own task and only logs failures, so it can never hold up or
+// fail the claim itself.
+pub fn send_claim_receipt(project: &str, wallet: &str, amount: i64, mint: &str, tx: &str) {
+    if !CONFIG.notify_on_claim {
+        return;
+    }
+
+    let project = project.to_string();
+    let wallet = wallet.to_string();
+    let subject = "Your EcoCoin airdrop receipt".to_string();
+    let text = format!(
+        "Your claim of {} tokens ({}) was sent to {}.\nTransaction: https://solscan.io/tx/{}",
+        amount, mint, wallet, tx
+    );
+    let receipt = json!({
+        "wallet": wallet,
+        "amount": amount,
+        "mint": mint,
+        "tx": tx,
+    });
+
+    tokio::spawn(async move {
+        let contact = match db::get_claim_contact(&project, &wallet).await {
+            Ok(Some(contact)) => contact,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::warn!(error = %e, %wallet, "Failed to load contact for claim receipt");
+                return;
+            }
+        };
+
+        let client = reqwest::Client::new();
+
+        if let Some(url) = &contact.contact_webhook_url {
+            deliver(client.post(url).json(&receipt), &wallet, "webhook").await;
+        }
+
+        if let (Some(to), Some(api_url)) = (&contact.contact_email, &CONFIG.email_api_url) {
+            let mut req = client.post(api_url).json(&json!({
+                "from": CONFIG.email_from,
+                "to": to,
+                "subject": subject,
+                "text": text,
+            }));
+            if let Some(key) = &CONFIG.email_api_key {
+                req = req.bearer_auth(key);
+            }
+            deliver(req, &wallet, "email").await;
+        }
+    });
+}
+
+async fn deliver(req: reqwest::RequestBuilder, wallet: &str, channel: &str) {
+    match req.timeout(RECEIPT_TIMEOUT).send().await {
+        Ok(res) if !res.status().is_success() => {
+            tracing::warn!(status = %res.status(), %wallet, channel, "Claim receipt rejected");
+        }
+        Ok(_) => {}
+        Err(e) => {
+            tracing::warn!(error = %e, %wallet, channel, "Failed to deliver claim receipt");
+        }
+    }
+}